@@ -0,0 +1,346 @@
+use crate::backtest::EquityCurve;
+
+//
+// --------------------
+// Per-symbol Contribution
+// --------------------
+// Splits each bar's change in account value into the P&L of every symbol
+// and a remainder for commissions, financing and cash interest. The first
+// bar is measured from the initial cash, so the totals add up to the final
+// account value minus the starting cash. Return contributions divide by the
+// previous bar's account value, so on any bar they add up to the account's
+// simple return.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolContribution {
+    pub symbol: String,
+    /// P&L per bar, aligned with `curve.dates`
+    pub pnl: Vec<f64>,
+    /// `pnl` over the previous bar's account value
+    pub contribution: Vec<f64>,
+}
+
+impl SymbolContribution {
+    pub fn total_pnl(&self) -> f64 {
+        self.pnl.iter().sum()
+    }
+
+    /// Sum of the per-bar contributions (not compounded)
+    pub fn total_contribution(&self) -> f64 {
+        self.contribution.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contributions {
+    pub symbols: Vec<SymbolContribution>,
+    /// Everything not earned by a position: fees, interest, borrow costs
+    pub other: SymbolContribution,
+}
+
+// `initial_cash` is the account value before the first bar
+pub fn contributions(curve: &EquityCurve, initial_cash: f64) -> Contributions {
+    let previous: Vec<f64> = std::iter::once(initial_cash)
+        .chain(curve.values.iter().copied())
+        .take(curve.len())
+        .collect();
+    let build = |symbol: &str, pnl: Vec<f64>| SymbolContribution {
+        symbol: symbol.to_string(),
+        contribution: pnl.iter().zip(&previous).map(|(p, v)| p / v).collect(),
+        pnl,
+    };
+
+    let symbols: Vec<SymbolContribution> = curve
+        .symbol_pnl
+        .iter()
+        .map(|(symbol, cumulative)| build(symbol, changes(0.0, cumulative)))
+        .collect();
+
+    let other = changes(initial_cash, &curve.values)
+        .into_iter()
+        .enumerate()
+        .map(|(t, change)| change - symbols.iter().map(|s| s.pnl[t]).sum::<f64>())
+        .collect();
+
+    Contributions {
+        other: build("other", other),
+        symbols,
+    }
+}
+
+// Change over every bar, the first one measured from `start`
+fn changes(start: f64, series: &[f64]) -> Vec<f64> {
+    std::iter::once(start)
+        .chain(series.iter().copied())
+        .collect::<Vec<_>>()
+        .windows(2)
+        .map(|w| w[1] - w[0])
+        .collect()
+}
+
+//
+// --------------------
+// Brinson–Fachler Attribution
+// --------------------
+// One period of one segment (an asset, sector or sleeve). Weights are the
+// shares of each side's value held at the start of the period and each side's
+// weights should sum to 1; returns are simple returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub name: String,
+    pub portfolio_weight: f64,
+    pub benchmark_weight: f64,
+    pub portfolio_return: f64,
+    pub benchmark_return: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentEffects {
+    pub name: String,
+    /// Over/underweighting segments that beat/lagged the whole benchmark
+    pub allocation: f64,
+    /// Beating the benchmark within the segment, at benchmark weight
+    pub selection: f64,
+    pub interaction: f64,
+}
+
+impl SegmentEffects {
+    pub fn total(&self) -> f64 {
+        self.allocation + self.selection + self.interaction
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribution {
+    pub portfolio_return: f64,
+    pub benchmark_return: f64,
+    pub segments: Vec<SegmentEffects>,
+}
+
+impl Attribution {
+    pub fn active_return(&self) -> f64 {
+        self.portfolio_return - self.benchmark_return
+    }
+
+    pub fn allocation(&self) -> f64 {
+        self.segments.iter().map(|s| s.allocation).sum()
+    }
+
+    pub fn selection(&self) -> f64 {
+        self.segments.iter().map(|s| s.selection).sum()
+    }
+
+    pub fn interaction(&self) -> f64 {
+        self.segments.iter().map(|s| s.interaction).sum()
+    }
+}
+
+// Single period. The three effects sum to the active return.
+pub fn brinson_fachler(segments: &[Segment]) -> Option<Attribution> {
+    if segments.is_empty() {
+        return None;
+    }
+    let portfolio_return = segments
+        .iter()
+        .map(|s| s.portfolio_weight * s.portfolio_return)
+        .sum();
+    let benchmark_return: f64 = segments
+        .iter()
+        .map(|s| s.benchmark_weight * s.benchmark_return)
+        .sum();
+
+    let effects = segments
+        .iter()
+        .map(|s| {
+            let active_weight = s.portfolio_weight - s.benchmark_weight;
+            let active_return = s.portfolio_return - s.benchmark_return;
+            SegmentEffects {
+                name: s.name.clone(),
+                allocation: active_weight * (s.benchmark_return - benchmark_return),
+                selection: s.benchmark_weight * active_return,
+                interaction: active_weight * active_return,
+            }
+        })
+        .collect();
+
+    Some(Attribution {
+        portfolio_return,
+        benchmark_return,
+        segments: effects,
+    })
+}
+
+// Several periods, linked with Carino's log-scaling so the effects add up to
+// the compounded active return. Segments are matched by name across periods.
+pub fn brinson_fachler_linked(periods: &[Vec<Segment>]) -> Option<Attribution> {
+    let singles: Vec<Attribution> = periods
+        .iter()
+        .map(|p| brinson_fachler(p))
+        .collect::<Option<_>>()?;
+    if singles.is_empty() {
+        return None;
+    }
+
+    let compound =
+        |f: fn(&Attribution) -> f64| singles.iter().map(|a| 1.0 + f(a)).product::<f64>() - 1.0;
+    let portfolio_return = compound(|a| a.portfolio_return);
+    let benchmark_return = compound(|a| a.benchmark_return);
+    let total_k = carino_factor(portfolio_return, benchmark_return);
+
+    let mut segments: Vec<SegmentEffects> = Vec::new();
+    for single in &singles {
+        let scale = carino_factor(single.portfolio_return, single.benchmark_return) / total_k;
+        for effect in &single.segments {
+            let linked = match segments.iter_mut().find(|s| s.name == effect.name) {
+                Some(s) => s,
+                None => {
+                    segments.push(SegmentEffects {
+                        name: effect.name.clone(),
+                        allocation: 0.0,
+                        selection: 0.0,
+                        interaction: 0.0,
+                    });
+                    segments.last_mut().unwrap()
+                }
+            };
+            linked.allocation += effect.allocation * scale;
+            linked.selection += effect.selection * scale;
+            linked.interaction += effect.interaction * scale;
+        }
+    }
+
+    Some(Attribution {
+        portfolio_return,
+        benchmark_return,
+        segments,
+    })
+}
+
+// (ln(1 + rp) - ln(1 + rb)) / (rp - rb), with its limit when they are equal
+fn carino_factor(rp: f64, rb: f64) -> f64 {
+    if (rp - rb).abs() < 1e-12 {
+        1.0 / (1.0 + rp)
+    } else {
+        (rp.ln_1p() - rb.ln_1p()) / (rp - rb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::{self, BacktestConfig, CashInterest};
+    use crate::data::Candle;
+    use crate::strategy::BuyAndHold;
+    use chrono::NaiveDate;
+
+    fn segment(name: &str, wp: f64, wb: f64, rp: f64, rb: f64) -> Segment {
+        Segment {
+            name: name.to_string(),
+            portfolio_weight: wp,
+            benchmark_weight: wb,
+            portfolio_return: rp,
+            benchmark_return: rb,
+        }
+    }
+
+    fn period() -> Vec<Segment> {
+        vec![
+            segment("Equity", 0.7, 0.6, 0.08, 0.05),
+            segment("Bonds", 0.3, 0.4, 0.02, 0.03),
+        ]
+    }
+
+    #[test]
+    fn test_brinson_fachler_single_period() {
+        let a = brinson_fachler(&period()).unwrap();
+        // Rp = 0.062, Rb = 0.042
+        assert!((a.portfolio_return - 0.062).abs() < 1e-12);
+        assert!((a.benchmark_return - 0.042).abs() < 1e-12);
+
+        let equity = &a.segments[0];
+        assert!((equity.allocation - 0.1 * (0.05 - 0.042)).abs() < 1e-12);
+        assert!((equity.selection - 0.6 * 0.03).abs() < 1e-12);
+        assert!((equity.interaction - 0.1 * 0.03).abs() < 1e-12);
+
+        let sum = a.allocation() + a.selection() + a.interaction();
+        assert!((sum - a.active_return()).abs() < 1e-12);
+        assert!(brinson_fachler(&[]).is_none());
+    }
+
+    #[test]
+    fn test_linked_effects_sum_to_compounded_active_return() {
+        let second = vec![
+            segment("Equity", 0.5, 0.6, -0.04, -0.06),
+            segment("Bonds", 0.5, 0.4, 0.01, 0.01),
+        ];
+        let a = brinson_fachler_linked(&[period(), second]).unwrap();
+
+        assert!((a.portfolio_return - (1.062 * (1.0 - 0.015) - 1.0)).abs() < 1e-12);
+        assert!((a.benchmark_return - (1.042 * (1.0 - 0.032) - 1.0)).abs() < 1e-12);
+        assert_eq!(a.segments.len(), 2);
+        let sum = a.allocation() + a.selection() + a.interaction();
+        assert!((sum - a.active_return()).abs() < 1e-12);
+    }
+
+    fn candles() -> Vec<Candle> {
+        [10.0, 11.0, 12.0, 10.5]
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| Candle {
+                date: NaiveDate::from_ymd_opt(2025, 9, 1 + i as u32).unwrap(),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 100.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_contributions_reconcile_with_equity() {
+        let candles = candles();
+        let config = BacktestConfig {
+            commission: 0.001,
+            ..Default::default()
+        };
+        let result = backtest::run(&candles, &mut BuyAndHold::default(), &config);
+        let c = contributions(&result.equity, config.initial_cash);
+
+        assert_eq!(c.symbols.len(), 1);
+        let asset = &c.symbols[0];
+        assert_eq!(asset.symbol, "ASSET");
+        assert_eq!(asset.pnl.len(), candles.len());
+
+        // bought at the second open of 11.0, held to the last close
+        let qty = result.portfolio.quantity("ASSET");
+        assert!(qty > 0.0);
+        assert!((asset.total_pnl() - qty * (10.5 - 11.0)).abs() < 1e-6);
+        assert!((c.other.total_pnl() + result.commissions).abs() < 1e-6);
+
+        let returns = result.equity.simple_returns();
+        for (t, r) in returns.iter().enumerate() {
+            let sum = asset.contribution[t + 1] + c.other.contribution[t + 1];
+            assert!((sum - r).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_contributions_include_first_bar_interest() {
+        let candles = candles();
+        let config = BacktestConfig {
+            cash_interest: Some(CashInterest::default()),
+            risk_free: vec![0.001; candles.len()],
+            ..Default::default()
+        };
+        let result = backtest::run(&candles, &mut BuyAndHold::default(), &config);
+        let c = contributions(&result.equity, config.initial_cash);
+
+        // the whole account is idle cash on the first bar
+        assert!((c.other.pnl[0] - config.initial_cash * 0.001).abs() < 1e-9);
+        assert!((c.other.total_pnl() - result.cash_interest).abs() < 1e-9);
+
+        let total: f64 = c.symbols.iter().map(|s| s.total_pnl()).sum::<f64>() + c.other.total_pnl();
+        let final_value = *result.equity.values.last().unwrap();
+        assert!((total - (final_value - config.initial_cash)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,2015 @@
+use crate::data::Candle;
+use crate::metrics::{self, ReturnKind, Returns, TRADING_DAYS_PER_YEAR};
+use crate::strategy::{Context, Order, PortfolioContext, PortfolioStrategy, Signal, Strategy};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+//
+// --------------------
+// Execution Timing
+// --------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Execution {
+    /// Signal computed on bar t's close fills at bar t+1's open
+    #[default]
+    NextOpen,
+    /// Signal computed on bar t's close fills at that same close. The strategy
+    /// trades on a price it only learns once the bar is over, so results are
+    /// optimistic ("cheating" mode).
+    SameClose,
+}
+
+impl Execution {
+    pub fn is_look_ahead(&self) -> bool {
+        matches!(self, Execution::SameClose)
+    }
+}
+
+impl fmt::Display for Execution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Execution::NextOpen => write!(f, "next bar open"),
+            Execution::SameClose => write!(f, "same bar close (LOOK-AHEAD)"),
+        }
+    }
+}
+
+//
+// --------------------
+// Short Selling
+// --------------------
+// Borrow rates are annualized and accrue per trading day on the short's
+// market value at the close.
+#[derive(Debug, Clone)]
+pub struct ShortConfig {
+    /// Annualized borrow rate per symbol (e.g. 0.05 = 5%)
+    pub borrow_rates: HashMap<String, f64>,
+    /// Rate used for symbols missing from `borrow_rates`
+    pub default_borrow_rate: f64,
+    /// Symbols that cannot be located; new shorts in them are rejected
+    pub hard_to_borrow: HashSet<String>,
+    /// Date from which the lender recalls the shares; any open short is
+    /// bought in at that bar's open and new shorts are rejected
+    pub recalls: HashMap<String, NaiveDate>,
+    /// Short is bought in at the close once the price is this fraction above
+    /// the average entry (e.g. 0.5 = +50%)
+    pub max_short_loss: Option<f64>,
+}
+
+impl Default for ShortConfig {
+    fn default() -> Self {
+        Self {
+            borrow_rates: HashMap::new(),
+            default_borrow_rate: 0.0025, // general collateral
+            hard_to_borrow: HashSet::new(),
+            recalls: HashMap::new(),
+            max_short_loss: None,
+        }
+    }
+}
+
+impl ShortConfig {
+    pub fn borrow_rate(&self, symbol: &str) -> f64 {
+        self.borrow_rates
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.default_borrow_rate)
+    }
+
+    /// Whether a borrow can be located for `symbol` on `date`
+    pub fn can_short(&self, symbol: &str, date: NaiveDate) -> bool {
+        !self.hard_to_borrow.contains(symbol) && !self.is_recalled(symbol, date)
+    }
+
+    fn is_recalled(&self, symbol: &str, date: NaiveDate) -> bool {
+        self.recalls
+            .get(symbol)
+            .is_some_and(|recall| date >= *recall)
+    }
+}
+
+//
+// --------------------
+// Margin
+// --------------------
+// Reg-T style account: buying power comes from equity / initial margin, and
+// the position is liquidated at the close whenever equity falls below the
+// maintenance requirement.
+#[derive(Debug, Clone)]
+pub struct MarginConfig {
+    /// Fraction of a new position's value that must be funded by equity
+    pub initial_margin: f64,
+    /// Minimum equity as a fraction of long market value
+    pub maintenance_margin: f64,
+    /// Minimum equity as a fraction of short market value
+    pub short_maintenance_margin: f64,
+    /// Cap on (long + short value) / equity
+    pub max_gross_leverage: f64,
+    /// Cap on |long - short value| / equity
+    pub max_net_leverage: f64,
+    /// Annual rate charged on a debit (negative cash) balance when the
+    /// backtest has no risk-free series
+    pub debit_base_rate: f64,
+    /// Annual spread added on top of the base rate
+    pub debit_spread: f64,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            initial_margin: 0.5,
+            maintenance_margin: 0.25,
+            short_maintenance_margin: 0.30,
+            max_gross_leverage: 2.0,
+            max_net_leverage: 2.0,
+            debit_base_rate: 0.05,
+            debit_spread: 0.01,
+        }
+    }
+}
+
+impl MarginConfig {
+    /// Largest position size (in shares, either side) the account may open
+    /// at `price` given its current equity
+    fn max_shares(&self, equity: f64, price: f64) -> f64 {
+        let leverage = (1.0 / self.initial_margin)
+            .min(self.max_gross_leverage)
+            .min(self.max_net_leverage);
+        (equity.max(0.0) * leverage / price).floor()
+    }
+
+    /// Equity the broker requires for a position of `position` shares
+    fn requirement(&self, position: f64, price: f64) -> f64 {
+        let rate = if position < 0.0 {
+            self.short_maintenance_margin
+        } else {
+            self.maintenance_margin
+        };
+        position.abs() * price * rate
+    }
+}
+
+//
+// --------------------
+// Cash Interest
+// --------------------
+// Idle cash is credited daily at the risk-free rate, less the haircut.
+// Short sale proceeds backing an open short do not count as idle.
+#[derive(Debug, Clone, Default)]
+pub struct CashInterest {
+    /// Fraction of the risk-free rate kept by the broker (e.g. 0.25)
+    pub haircut: f64,
+}
+
+//
+// --------------------
+// Portfolio Ledger
+// --------------------
+// Positions are kept as lots so that realized P&L follows the chosen lot
+// method. Quantities are signed: a short is a stack of negative lots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LotMethod {
+    /// Closing trades match the oldest open lots first
+    #[default]
+    Fifo,
+    /// Closing trades match the newest open lots first
+    Lifo,
+    /// All open shares carry one blended cost
+    AverageCost,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lot {
+    pub date: NaiveDate,
+    pub qty: f64,
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Position {
+    lots: VecDeque<Lot>,
+    pub realized_pnl: f64,
+    /// Price the position was last marked at
+    pub last_price: f64,
+}
+
+impl Position {
+    pub fn quantity(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.qty).sum()
+    }
+
+    /// Average cost of the open lots, 0.0 when flat
+    pub fn avg_cost(&self) -> f64 {
+        let qty = self.quantity();
+        if qty == 0.0 {
+            return 0.0;
+        }
+        self.lots.iter().map(|lot| lot.qty * lot.price).sum::<f64>() / qty
+    }
+
+    pub fn lots(&self) -> impl Iterator<Item = &Lot> {
+        self.lots.iter()
+    }
+
+    pub fn market_value(&self) -> f64 {
+        self.quantity() * self.last_price
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.lots
+            .iter()
+            .map(|lot| lot.qty * (self.last_price - lot.price))
+            .sum()
+    }
+
+    // Returns the P&L realized by this trade
+    fn apply(&mut self, qty: f64, price: f64, date: NaiveDate, method: LotMethod) -> f64 {
+        let mut remaining = qty;
+        let mut realized = 0.0;
+
+        // close against open lots on the other side
+        while remaining != 0.0 {
+            let lot = match method {
+                LotMethod::Lifo => self.lots.back_mut(),
+                LotMethod::Fifo | LotMethod::AverageCost => self.lots.front_mut(),
+            };
+            let Some(lot) = lot else { break };
+            if lot.qty.signum() == remaining.signum() {
+                break;
+            }
+
+            let matched = remaining.abs().min(lot.qty.abs()) * lot.qty.signum();
+            realized += matched * (price - lot.price);
+            lot.qty -= matched;
+            remaining += matched;
+            if lot.qty == 0.0 {
+                match method {
+                    LotMethod::Lifo => self.lots.pop_back(),
+                    LotMethod::Fifo | LotMethod::AverageCost => self.lots.pop_front(),
+                };
+            }
+        }
+
+        // whatever is left opens (or adds to) a position
+        if remaining != 0.0 {
+            match (method, self.lots.front_mut()) {
+                (LotMethod::AverageCost, Some(lot)) => {
+                    let qty = lot.qty + remaining;
+                    lot.price = (lot.qty * lot.price + remaining * price) / qty;
+                    lot.qty = qty;
+                }
+                _ => self.lots.push_back(Lot {
+                    date,
+                    qty: remaining,
+                    price,
+                }),
+            }
+        }
+
+        self.realized_pnl += realized;
+        self.last_price = price;
+        realized
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    pub cash: f64,
+    method: LotMethod,
+    positions: BTreeMap<String, Position>,
+}
+
+impl Portfolio {
+    pub fn new(cash: f64, method: LotMethod) -> Self {
+        Self {
+            cash,
+            method,
+            positions: BTreeMap::new(),
+        }
+    }
+
+    /// Books a trade of `qty` shares (negative to sell) and moves the cash.
+    /// Returns the realized P&L.
+    pub fn apply_fill(&mut self, symbol: &str, qty: f64, price: f64, date: NaiveDate) -> f64 {
+        self.cash -= qty * price;
+        self.positions
+            .entry(symbol.to_string())
+            .or_default()
+            .apply(qty, price, date, self.method)
+    }
+
+    /// Marks a symbol's position at `price`
+    pub fn mark(&mut self, symbol: &str, price: f64) {
+        if let Some(position) = self.positions.get_mut(symbol) {
+            position.last_price = price;
+        }
+    }
+
+    pub fn position(&self, symbol: &str) -> Option<&Position> {
+        self.positions.get(symbol)
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (&String, &Position)> {
+        self.positions.iter()
+    }
+
+    pub fn quantity(&self, symbol: &str) -> f64 {
+        self.position(symbol).map_or(0.0, Position::quantity)
+    }
+
+    /// Net market value of all positions (shorts count negative)
+    pub fn market_value(&self) -> f64 {
+        self.positions.values().map(Position::market_value).sum()
+    }
+
+    /// Long plus short market value
+    pub fn gross_exposure(&self) -> f64 {
+        self.positions
+            .values()
+            .map(|p| p.market_value().abs())
+            .sum()
+    }
+
+    pub fn equity(&self) -> f64 {
+        self.cash + self.market_value()
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.positions.values().map(|p| p.realized_pnl).sum()
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.positions.values().map(Position::unrealized_pnl).sum()
+    }
+}
+
+//
+// --------------------
+// Equity Curve
+// --------------------
+// One entry per bar, recorded after the close
+#[derive(Debug, Clone, Default)]
+pub struct EquityCurve {
+    pub dates: Vec<NaiveDate>,
+    /// Total account value (cash + market value)
+    pub values: Vec<f64>,
+    pub cash: Vec<f64>,
+    pub market_value: Vec<f64>,
+    /// Cumulative realized plus unrealized P&L per symbol, before fees and
+    /// financing (zero before the symbol's first fill)
+    pub symbol_pnl: BTreeMap<String, Vec<f64>>,
+    /// Market value per symbol
+    pub symbol_value: BTreeMap<String, Vec<f64>>,
+}
+
+impl EquityCurve {
+    pub fn record(&mut self, date: NaiveDate, portfolio: &Portfolio) {
+        let bar = self.values.len();
+        self.dates.push(date);
+        self.values.push(portfolio.equity());
+        self.cash.push(portfolio.cash);
+        self.market_value.push(portfolio.market_value());
+
+        for (symbol, position) in portfolio.positions() {
+            let pnl = position.realized_pnl + position.unrealized_pnl();
+            for (series, value) in [
+                (&mut self.symbol_pnl, pnl),
+                (&mut self.symbol_value, position.market_value()),
+            ] {
+                series
+                    .entry(symbol.clone())
+                    .or_insert_with(|| vec![0.0; bar])
+                    .push(value);
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Log returns of the account value, the same kind `metrics::daily_returns`
+    /// produces from candle closes
+    pub fn returns(&self) -> Vec<f64> {
+        metrics::log_returns(&self.values)
+    }
+
+    /// Simple returns of the account value, for means, ratios and betas
+    pub fn simple_returns(&self) -> Vec<f64> {
+        metrics::simple_returns(&self.values)
+    }
+}
+
+//
+// --------------------
+// Config & Results
+// --------------------
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub symbol: String,
+    pub initial_cash: f64,
+    pub execution: Execution,
+    pub lot_method: LotMethod,
+    /// Commission as a fraction of traded notional (e.g. 0.001 = 10bp)
+    pub commission: f64,
+    /// Adverse price move applied to every fill as a fraction of the price
+    pub slippage: f64,
+    /// `None` keeps the account long-only
+    pub shorting: Option<ShortConfig>,
+    /// `None` keeps the account cash-only (no leverage, no debit balance)
+    pub margin: Option<MarginConfig>,
+    /// `None` leaves idle cash uninvested
+    pub cash_interest: Option<CashInterest>,
+    /// Daily risk-free returns, one per bar and matched to the bar dates (e.g.
+    /// from `data::risk_free_for_dates`). The last value is reused if the
+    /// series is shorter than the data.
+    pub risk_free: Vec<f64>,
+}
+
+impl BacktestConfig {
+    fn risk_free_daily(&self, bar: usize) -> Option<f64> {
+        self.risk_free
+            .get(bar)
+            .or_else(|| self.risk_free.last())
+            .copied()
+    }
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            symbol: "ASSET".to_string(),
+            initial_cash: 100_000.0,
+            execution: Execution::default(),
+            lot_method: LotMethod::default(),
+            commission: 0.0,
+            slippage: 0.0,
+            shorting: None,
+            margin: None,
+            cash_interest: None,
+            risk_free: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub date: NaiveDate,
+    pub symbol: String,
+    /// Execution price, slippage included
+    pub price: f64,
+    /// Positive for buys, negative for sells
+    pub qty: f64,
+    pub fee: f64,
+}
+
+//
+// --------------------
+// Round-trip Trades
+// --------------------
+// A trade opens when a position leaves flat and closes when it returns to
+// flat. A fill that flips the position closes one trade and opens the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub symbol: String,
+    pub entry_date: NaiveDate,
+    pub exit_date: NaiveDate,
+    /// Average price of the fills that built the position
+    pub entry_price: f64,
+    /// Average price of the fills that closed it
+    pub exit_price: f64,
+    /// Total shares entered; negative for a short
+    pub qty: f64,
+    pub fees: f64,
+    /// Cost of slippage across all of the trade's fills
+    pub slippage: f64,
+    /// Realized P&L net of fees
+    pub pnl: f64,
+    /// Worst open P&L seen while the trade was on (<= 0)
+    pub mae: f64,
+    /// Best open P&L seen while the trade was on (>= 0)
+    pub mfe: f64,
+    pub bars_held: usize,
+}
+
+impl Trade {
+    pub fn holding_days(&self) -> i64 {
+        (self.exit_date - self.entry_date).num_days()
+    }
+}
+
+#[derive(Debug)]
+struct OpenTrade {
+    entry_date: NaiveDate,
+    entry_bar: usize,
+    entry_qty: f64,
+    entry_value: f64,
+    exit_qty: f64,
+    exit_value: f64,
+    fees: f64,
+    slippage: f64,
+    realized: f64,
+    mae: f64,
+    mfe: f64,
+}
+
+impl OpenTrade {
+    fn new(date: NaiveDate, bar: usize) -> Self {
+        Self {
+            entry_date: date,
+            entry_bar: bar,
+            entry_qty: 0.0,
+            entry_value: 0.0,
+            exit_qty: 0.0,
+            exit_value: 0.0,
+            fees: 0.0,
+            slippage: 0.0,
+            realized: 0.0,
+            mae: 0.0,
+            mfe: 0.0,
+        }
+    }
+
+    fn close(self, symbol: &str, date: NaiveDate, bar: usize) -> Trade {
+        Trade {
+            symbol: symbol.to_string(),
+            entry_date: self.entry_date,
+            exit_date: date,
+            entry_price: self.entry_value / self.entry_qty,
+            exit_price: self.exit_value / self.exit_qty,
+            qty: self.entry_qty,
+            fees: self.fees,
+            slippage: self.slippage,
+            pnl: self.realized - self.fees,
+            mae: self.mae,
+            mfe: self.mfe,
+            bars_held: bar - self.entry_bar,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// Short (or the short part of a sell) refused because no borrow was found
+    ShortRejected { qty: f64 },
+    /// Short closed by the broker rather than the strategy
+    BuyIn {
+        qty: f64,
+        price: f64,
+        reason: BuyInReason,
+    },
+    /// Order cut down to stay within margin and leverage limits, or within
+    /// equity for a short on a cash account
+    OrderCapped { requested: f64, filled: f64 },
+    /// Equity fell below maintenance; `qty` shares were liquidated at `price`
+    MarginCall {
+        equity: f64,
+        requirement: f64,
+        qty: f64,
+        price: f64,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuyInReason {
+    Recall,
+    LossLimit,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub date: NaiveDate,
+    pub kind: EventKind,
+}
+
+#[derive(Debug)]
+pub struct BacktestResult {
+    pub execution: Execution,
+    /// Account value at each bar's close
+    pub equity: EquityCurve,
+    pub fills: Vec<Fill>,
+    pub events: Vec<Event>,
+    /// Total borrow fees charged on short positions
+    pub borrow_fees: f64,
+    /// Total interest charged on debit balances
+    pub debit_interest: f64,
+    /// Total interest credited on idle cash
+    pub cash_interest: f64,
+    /// Total commissions paid
+    pub commissions: f64,
+    /// Closed round trips, in the order they were closed
+    pub trades: Vec<Trade>,
+    /// Account state after the last bar
+    pub portfolio: Portfolio,
+}
+
+//
+// --------------------
+// Account
+// --------------------
+struct Account {
+    symbol: String,
+    commission: f64,
+    slippage: f64,
+    portfolio: Portfolio,
+    // index of the bar being processed
+    bar: usize,
+    borrow_fees: f64,
+    debit_interest: f64,
+    cash_interest: f64,
+    commissions: f64,
+    fills: Vec<Fill>,
+    events: Vec<Event>,
+    open_trade: Option<OpenTrade>,
+    trades: Vec<Trade>,
+}
+
+impl Account {
+    fn new(config: &BacktestConfig) -> Self {
+        Self {
+            symbol: config.symbol.clone(),
+            commission: config.commission,
+            slippage: config.slippage,
+            portfolio: Portfolio::new(config.initial_cash, config.lot_method),
+            bar: 0,
+            borrow_fees: 0.0,
+            debit_interest: 0.0,
+            cash_interest: 0.0,
+            commissions: 0.0,
+            fills: Vec::new(),
+            events: Vec::new(),
+            open_trade: None,
+            trades: Vec::new(),
+        }
+    }
+
+    fn position(&self) -> f64 {
+        self.portfolio.quantity(&self.symbol)
+    }
+
+    fn equity(&self, price: f64) -> f64 {
+        self.portfolio.cash + self.position() * price
+    }
+
+    // Buys are capped by cash (or buying power on margin); sells by the
+    // shares held unless shorting is on
+    fn execute(&mut self, signal: Signal, price: f64, date: NaiveDate, config: &BacktestConfig) {
+        let qty = match signal {
+            Signal::Hold => return,
+            Signal::Buy(q) if config.margin.is_some() => q,
+            Signal::Buy(q) => {
+                let cost = price * (1.0 + self.slippage) * (1.0 + self.commission);
+                q.min((self.portfolio.cash / cost).floor())
+            }
+            Signal::Sell(q) => {
+                let held = self.position().max(0.0);
+                match &config.shorting {
+                    None => -q.min(held),
+                    Some(short) if q > held && !short.can_short(&config.symbol, date) => {
+                        self.events.push(Event {
+                            date,
+                            kind: EventKind::ShortRejected { qty: q - held },
+                        });
+                        -held
+                    }
+                    Some(_) => -q,
+                }
+            }
+        };
+        let qty = match &config.margin {
+            Some(margin) => self.cap_to_margin(qty, price, date, margin),
+            None if qty < 0.0 && config.shorting.is_some() => self.cap_short(qty, price, date),
+            None => qty,
+        };
+        self.fill(qty, price, date);
+    }
+
+    // Trades that reduce exposure always go through; trades that add to it
+    // stop at the leverage limit
+    fn cap_to_margin(
+        &mut self,
+        qty: f64,
+        price: f64,
+        date: NaiveDate,
+        margin: &MarginConfig,
+    ) -> f64 {
+        let position = self.position();
+        let limit = margin.max_shares(self.equity(price), price);
+        let mut target = position + qty;
+        if target > limit {
+            target = limit.max(position.min(target));
+        } else if target < -limit {
+            target = (-limit).min(position.max(target));
+        }
+
+        self.record_cap(qty, target - position, date)
+    }
+
+    // Without margin the short proceeds cannot fund more than one times
+    // equity of short exposure; a short already past that gets no bigger
+    fn cap_short(&mut self, qty: f64, price: f64, date: NaiveDate) -> f64 {
+        let position = self.position();
+        let limit = (self.equity(price).max(0.0) / price).floor();
+        if position + qty >= -limit {
+            return qty;
+        }
+        let target = (-limit).min(position);
+        self.record_cap(qty, target - position, date)
+    }
+
+    fn record_cap(&mut self, requested: f64, filled: f64, date: NaiveDate) -> f64 {
+        if filled != requested {
+            self.events.push(Event {
+                date,
+                kind: EventKind::OrderCapped { requested, filled },
+            });
+        }
+        filled
+    }
+
+    // `price` is the quoted price; slippage and commission are applied here
+    fn fill(&mut self, qty: f64, price: f64, date: NaiveDate) {
+        if qty == 0.0 || !qty.is_finite() {
+            return;
+        }
+
+        let exec_price = price * (1.0 + qty.signum() * self.slippage);
+        let fee = qty.abs() * exec_price * self.commission;
+        let slippage = qty.abs() * price * self.slippage;
+
+        // split the fill into the part closing the current trade and the
+        // part opening (or adding to) one
+        let position = self.position();
+        let closing = if position != 0.0 && position.signum() != qty.signum() {
+            qty.abs().min(position.abs()) * qty.signum()
+        } else {
+            0.0
+        };
+        let opening = qty - closing;
+
+        let realized = self
+            .portfolio
+            .apply_fill(&self.symbol, qty, exec_price, date);
+        self.portfolio.cash -= fee;
+        self.commissions += fee;
+
+        if closing != 0.0
+            && let Some(mut trade) = self.open_trade.take()
+        {
+            let share = closing / qty;
+            trade.exit_qty += closing.abs();
+            trade.exit_value += closing.abs() * exec_price;
+            trade.fees += fee * share;
+            trade.slippage += slippage * share;
+            trade.realized += realized;
+            if position + closing == 0.0 {
+                self.trades.push(trade.close(&self.symbol, date, self.bar));
+            } else {
+                self.open_trade = Some(trade);
+            }
+        }
+        if opening != 0.0 {
+            let share = opening / qty;
+            let trade = self
+                .open_trade
+                .get_or_insert_with(|| OpenTrade::new(date, self.bar));
+            trade.entry_qty += opening;
+            trade.entry_value += opening * exec_price;
+            trade.fees += fee * share;
+            trade.slippage += slippage * share;
+        }
+
+        self.fills.push(Fill {
+            date,
+            symbol: self.symbol.clone(),
+            price: exec_price,
+            qty,
+            fee,
+        });
+    }
+
+    // Open P&L at the bar's high and low bounds the trade's excursions
+    fn track_excursion(&mut self, bar: &Candle) {
+        let Some(trade) = &mut self.open_trade else {
+            return;
+        };
+        let Some(position) = self.portfolio.position(&self.symbol) else {
+            return;
+        };
+
+        let qty = position.quantity();
+        let cost = position.avg_cost();
+        let at_high = qty * (bar.high - cost);
+        let at_low = qty * (bar.low - cost);
+        trade.mae = trade.mae.min(at_high.min(at_low));
+        trade.mfe = trade.mfe.max(at_high.max(at_low));
+    }
+
+    fn buy_in(&mut self, price: f64, date: NaiveDate, reason: BuyInReason) {
+        let qty = -self.position();
+        self.fill(qty, price, date);
+        self.events.push(Event {
+            date,
+            kind: EventKind::BuyIn { qty, price, reason },
+        });
+    }
+
+    // Recalls are enforced at the open, before any pending order fills
+    fn check_recall(&mut self, bar: &Candle, config: &BacktestConfig) {
+        let Some(short) = &config.shorting else {
+            return;
+        };
+        if self.position() < 0.0 && short.is_recalled(&config.symbol, bar.date) {
+            self.buy_in(bar.open, bar.date, BuyInReason::Recall);
+        }
+    }
+
+    // Borrow fee accrual and the loss-limit buy-in both happen at the close
+    fn settle_short(&mut self, bar: &Candle, config: &BacktestConfig) {
+        let Some(short) = &config.shorting else {
+            return;
+        };
+        let position = self.position();
+        if position >= 0.0 {
+            return;
+        }
+
+        let rate = short.borrow_rate(&config.symbol);
+        let fee = position.abs() * bar.close * rate / TRADING_DAYS_PER_YEAR as f64;
+        self.portfolio.cash -= fee;
+        self.borrow_fees += fee;
+
+        let avg_cost = self
+            .portfolio
+            .position(&self.symbol)
+            .map_or(0.0, Position::avg_cost);
+        if let Some(limit) = short.max_short_loss
+            && bar.close >= avg_cost * (1.0 + limit)
+        {
+            self.buy_in(bar.close, bar.date, BuyInReason::LossLimit);
+        }
+    }
+
+    // Debit interest accrues daily at the risk-free rate (or the configured
+    // base rate) plus the spread
+    fn accrue_debit_interest(&mut self, bar: usize, config: &BacktestConfig) {
+        let Some(margin) = &config.margin else {
+            return;
+        };
+        let cash = self.portfolio.cash;
+        if cash >= 0.0 {
+            return;
+        }
+
+        let spread = margin.debit_spread / TRADING_DAYS_PER_YEAR as f64;
+        let base = config
+            .risk_free_daily(bar)
+            .unwrap_or(margin.debit_base_rate / TRADING_DAYS_PER_YEAR as f64);
+        let interest = -cash * (base + spread);
+        self.portfolio.cash -= interest;
+        self.debit_interest += interest;
+    }
+
+    fn accrue_cash_interest(&mut self, bar: &Candle, t: usize, config: &BacktestConfig) {
+        let Some(cash_interest) = &config.cash_interest else {
+            return;
+        };
+        let Some(rf) = config.risk_free_daily(t) else {
+            return;
+        };
+
+        let idle = self.portfolio.cash + self.position().min(0.0) * bar.close;
+        if idle <= 0.0 {
+            return;
+        }
+        let interest = idle * rf * (1.0 - cash_interest.haircut);
+        self.portfolio.cash += interest;
+        self.cash_interest += interest;
+    }
+
+    // Below maintenance, sell down (or cover) to the initial margin level
+    fn check_margin(&mut self, bar: &Candle, config: &BacktestConfig) {
+        let Some(margin) = &config.margin else {
+            return;
+        };
+        let position = self.position();
+        let equity = self.equity(bar.close);
+        let requirement = margin.requirement(position, bar.close);
+        if position == 0.0 || equity >= requirement {
+            return;
+        }
+
+        let keep = margin.max_shares(equity, bar.close).min(position.abs());
+        let qty = -position.signum() * (position.abs() - keep);
+        self.fill(qty, bar.close, bar.date);
+        self.events.push(Event {
+            date: bar.date,
+            kind: EventKind::MarginCall {
+                equity,
+                requirement,
+                qty,
+                price: bar.close,
+            },
+        });
+    }
+}
+
+//
+// --------------------
+// Engine
+// --------------------
+pub fn run<S: Strategy + ?Sized>(
+    candles: &[Candle],
+    strategy: &mut S,
+    config: &BacktestConfig,
+) -> BacktestResult {
+    let mut account = Account::new(config);
+    let mut equity = EquityCurve::default();
+
+    // a NextOpen signal waits here until the following bar opens
+    let mut pending: Option<Signal> = None;
+
+    for (t, bar) in candles.iter().enumerate() {
+        account.bar = t;
+        account.check_recall(bar, config);
+        if let Some(signal) = pending.take() {
+            account.execute(signal, bar.open, bar.date, config);
+        }
+        // the bar's range plays out before its close is known
+        account.track_excursion(bar);
+
+        let ctx = Context::new(candles, t, account.position(), account.portfolio.cash);
+        let signal = strategy.on_bar(&ctx);
+
+        match config.execution {
+            Execution::NextOpen => pending = Some(signal),
+            Execution::SameClose => account.execute(signal, bar.close, bar.date, config),
+        }
+
+        account.settle_short(bar, config);
+        account.accrue_debit_interest(t, config);
+        account.accrue_cash_interest(bar, t, config);
+        account.check_margin(bar, config);
+
+        account.portfolio.mark(&config.symbol, bar.close);
+        equity.record(bar.date, &account.portfolio);
+    }
+
+    BacktestResult {
+        execution: config.execution,
+        equity,
+        fills: account.fills,
+        events: account.events,
+        borrow_fees: account.borrow_fees,
+        debit_interest: account.debit_interest,
+        cash_interest: account.cash_interest,
+        commissions: account.commissions,
+        trades: account.trades,
+        portfolio: account.portfolio,
+    }
+}
+
+//
+// --------------------
+// Portfolio Engine
+// --------------------
+// Runs a multi-asset strategy over the dates every symbol has a candle for
+// (each series in ascending date order). The account is cash-only and
+// long-only: commission, slippage, lot method and timing come from `config`,
+// while its symbol, shorting, margin and cash interest settings are not used
+// and round-trip trades are not tracked.
+pub fn run_portfolio<S: PortfolioStrategy + ?Sized>(
+    candles: &BTreeMap<String, Vec<Candle>>,
+    strategy: &mut S,
+    config: &BacktestConfig,
+) -> BacktestResult {
+    let index: BTreeMap<&str, HashMap<NaiveDate, usize>> = candles
+        .iter()
+        .map(|(s, series)| {
+            let by_date = series
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (c.date, i))
+                .collect();
+            (s.as_str(), by_date)
+        })
+        .collect();
+    let dates: Vec<NaiveDate> = match candles.values().next() {
+        Some(first) => first
+            .iter()
+            .map(|c| c.date)
+            .filter(|d| index.values().all(|m| m.contains_key(d)))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut portfolio = Portfolio::new(config.initial_cash, config.lot_method);
+    let mut equity = EquityCurve::default();
+    let mut fills = Vec::new();
+    let mut pending: Vec<Order> = Vec::new();
+
+    for date in dates {
+        let bars: BTreeMap<&str, &Candle> = index
+            .iter()
+            .map(|(s, m)| (*s, &candles[*s][m[&date]]))
+            .collect();
+        let execute = |orders: Vec<Order>, at_open: bool, portfolio: &mut Portfolio| {
+            orders
+                .into_iter()
+                .filter_map(|order| {
+                    let bar = bars.get(order.symbol.as_str())?;
+                    let price = if at_open { bar.open } else { bar.close };
+                    fill_order(portfolio, &order, price, date, config)
+                })
+                .collect::<Vec<Fill>>()
+        };
+
+        let opened = execute(std::mem::take(&mut pending), true, &mut portfolio);
+        fills.extend(opened);
+
+        let histories = index
+            .iter()
+            .map(|(s, m)| (*s, &candles[*s][..=m[&date]]))
+            .collect();
+        let orders = strategy.on_bar(&PortfolioContext::new(date, histories, &portfolio));
+        match config.execution {
+            Execution::NextOpen => pending = orders,
+            Execution::SameClose => fills.extend(execute(orders, false, &mut portfolio)),
+        }
+
+        for (symbol, bar) in &bars {
+            portfolio.mark(symbol, bar.close);
+        }
+        equity.record(date, &portfolio);
+    }
+    let commissions = fills.iter().map(|f| f.fee).sum();
+
+    BacktestResult {
+        execution: config.execution,
+        equity,
+        fills,
+        events: Vec::new(),
+        borrow_fees: 0.0,
+        debit_interest: 0.0,
+        cash_interest: 0.0,
+        commissions,
+        trades: Vec::new(),
+        portfolio,
+    }
+}
+
+// Sells are capped at the shares held and buys at the whole lots the cash
+// covers
+fn fill_order(
+    portfolio: &mut Portfolio,
+    order: &Order,
+    price: f64,
+    date: NaiveDate,
+    config: &BacktestConfig,
+) -> Option<Fill> {
+    let qty = if order.qty < 0.0 {
+        -(-order.qty).min(portfolio.quantity(&order.symbol).max(0.0))
+    } else {
+        let cost = price * (1.0 + config.slippage) * (1.0 + config.commission);
+        let affordable = (portfolio.cash / (cost * order.lot)).floor() * order.lot;
+        order.qty.min(affordable.max(0.0))
+    };
+    if qty == 0.0 || !qty.is_finite() {
+        return None;
+    }
+    let exec_price = price * (1.0 + qty.signum() * config.slippage);
+    let fee = qty.abs() * exec_price * config.commission;
+    portfolio.apply_fill(&order.symbol, qty, exec_price, date);
+    portfolio.cash -= fee;
+    Some(Fill {
+        date,
+        symbol: order.symbol.clone(),
+        price: exec_price,
+        qty,
+        fee,
+    })
+}
+
+//
+// --------------------
+// Parameter Search
+// --------------------
+// Every configuration tried by `grid_search`, with its per-period Sharpe
+#[derive(Debug, Clone)]
+pub struct Trial<P> {
+    pub params: P,
+    /// `None` if the equity curve has no Sharpe (e.g. it never moved)
+    pub sharpe: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct SearchReport<P> {
+    /// Parameters with the highest in-sample Sharpe
+    pub best: P,
+    pub result: BacktestResult,
+    /// Per-period Sharpe of the winner
+    pub sharpe: f64,
+    /// Probability the winner's true Sharpe is above zero
+    pub psr: Option<f64>,
+    /// PSR against the best Sharpe expected by chance from `trials.len()` tries
+    pub dsr: Option<f64>,
+    pub trials: Vec<Trial<P>>,
+}
+
+// Backtests one strategy per parameter set and keeps the best Sharpe. The
+// winner is reported with its deflated Sharpe, since picking the maximum of
+// many trials inflates the in-sample figure.
+pub fn grid_search<P, S, F>(
+    candles: &[Candle],
+    params: &[P],
+    config: &BacktestConfig,
+    mut make: F,
+) -> Option<SearchReport<P>>
+where
+    P: Clone,
+    S: Strategy,
+    F: FnMut(&P) -> S,
+{
+    let mut trials: Vec<Trial<P>> = Vec::with_capacity(params.len());
+    let mut best: Option<(usize, f64, BacktestResult)> = None;
+
+    for p in params {
+        let result = run(candles, &mut make(p), config);
+        let returns = Returns::new(ReturnKind::Simple, result.equity.simple_returns());
+        let sharpe = metrics::per_period_sharpe(&returns);
+        if let Some(sharpe) = sharpe
+            && best.as_ref().is_none_or(|(_, top, _)| sharpe > *top)
+        {
+            best = Some((trials.len(), sharpe, result));
+        }
+        trials.push(Trial {
+            params: p.clone(),
+            sharpe,
+        });
+    }
+
+    let (index, sharpe, result) = best?;
+    let returns = Returns::new(ReturnKind::Simple, result.equity.simple_returns());
+    // every trial counts towards the deflation, with or without a Sharpe
+    let sharpes: Vec<Option<f64>> = trials.iter().map(|t| t.sharpe).collect();
+    Some(SearchReport {
+        best: trials[index].params.clone(),
+        sharpe,
+        psr: metrics::probabilistic_sharpe_ratio(&returns, 0.0),
+        dsr: metrics::deflated_sharpe_ratio(&returns, &sharpes),
+        result,
+        trials,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::BuyAndHold;
+    use crate::test_util::day;
+
+    fn candle(day: u32, open: f64, close: f64) -> Candle {
+        Candle {
+            date: NaiveDate::from_ymd_opt(2025, 9, day).unwrap(),
+            open,
+            high: open.max(close),
+            low: open.min(close),
+            close,
+            volume: 100.0,
+        }
+    }
+
+    fn position(result: &BacktestResult) -> f64 {
+        result
+            .portfolio
+            .positions()
+            .map(|(_, p)| p.quantity())
+            .sum()
+    }
+
+    fn sample() -> Vec<Candle> {
+        vec![
+            candle(1, 10.0, 11.0),
+            candle(2, 12.0, 13.0),
+            candle(3, 14.0, 15.0),
+        ]
+    }
+
+    // Tries to read the bar after the current one on every call
+    struct Peeker {
+        saw_future: bool,
+        seen: Vec<usize>,
+    }
+
+    impl Strategy for Peeker {
+        fn on_bar(&mut self, ctx: &Context) -> Signal {
+            if ctx.history().get(ctx.index() + 1).is_some() {
+                self.saw_future = true;
+            }
+            self.seen.push(ctx.history().len());
+            Signal::Hold
+        }
+    }
+
+    #[test]
+    fn test_strategy_cannot_peek_at_future_bars() {
+        let candles = sample();
+        let mut s = Peeker {
+            saw_future: false,
+            seen: Vec::new(),
+        };
+        run(&candles, &mut s, &BacktestConfig::default());
+
+        assert!(!s.saw_future);
+        assert_eq!(s.seen, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_next_open_fills_on_following_bar() {
+        let candles = sample();
+        let config = BacktestConfig {
+            initial_cash: 1_000.0,
+            execution: Execution::NextOpen,
+            ..Default::default()
+        };
+        let result = run(&candles, &mut BuyAndHold::default(), &config);
+
+        // signal on day 1's close, filled at day 2's open (12.0)
+        assert_eq!(result.fills.len(), 1);
+        assert_eq!(result.fills[0].date, candles[1].date);
+        assert_eq!(result.fills[0].price, 12.0);
+        assert_eq!(result.fills[0].qty, 83.0);
+        // nothing held on the first bar
+        assert_eq!(result.equity.values[0], 1_000.0);
+    }
+
+    #[test]
+    fn test_same_close_fills_on_signal_bar() {
+        let candles = sample();
+        let config = BacktestConfig {
+            initial_cash: 1_000.0,
+            execution: Execution::SameClose,
+            ..Default::default()
+        };
+        let result = run(&candles, &mut BuyAndHold::default(), &config);
+
+        assert!(result.execution.is_look_ahead());
+        assert_eq!(result.fills[0].date, candles[0].date);
+        assert_eq!(result.fills[0].price, 11.0);
+        assert_eq!(result.fills[0].qty, 90.0);
+    }
+
+    #[test]
+    fn test_signal_on_last_bar_is_never_filled() {
+        struct BuyLast;
+        impl Strategy for BuyLast {
+            fn on_bar(&mut self, ctx: &Context) -> Signal {
+                if ctx.index() == 2 {
+                    Signal::Buy(1.0)
+                } else {
+                    Signal::Hold
+                }
+            }
+        }
+
+        let result = run(&sample(), &mut BuyLast, &BacktestConfig::default());
+        assert!(result.fills.is_empty());
+        assert_eq!(position(&result), 0.0);
+    }
+
+    // Sells `qty` on the first bar, then holds
+    struct ShortOnce(f64);
+
+    impl Strategy for ShortOnce {
+        fn on_bar(&mut self, ctx: &Context) -> Signal {
+            if ctx.index() == 0 {
+                Signal::Sell(self.0)
+            } else {
+                Signal::Hold
+            }
+        }
+    }
+
+    fn short_config(shorting: ShortConfig) -> BacktestConfig {
+        BacktestConfig {
+            symbol: "XYZ".to_string(),
+            initial_cash: 1_000.0,
+            execution: Execution::SameClose,
+            shorting: Some(shorting),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_long_only_sell_cannot_go_negative() {
+        let config = BacktestConfig {
+            execution: Execution::SameClose,
+            ..Default::default()
+        };
+        let result = run(&sample(), &mut ShortOnce(10.0), &config);
+        assert!(result.fills.is_empty());
+        assert_eq!(position(&result), 0.0);
+    }
+
+    #[test]
+    fn test_short_sale_and_borrow_fee() {
+        let config = short_config(ShortConfig {
+            default_borrow_rate: 0.0,
+            borrow_rates: HashMap::from([("XYZ".to_string(), 0.252)]),
+            ..Default::default()
+        });
+        let result = run(&sample(), &mut ShortOnce(10.0), &config);
+
+        assert_eq!(position(&result), -10.0);
+        assert_eq!(result.fills[0].qty, -10.0);
+        // 0.1% of the short's value per day: 10 * (11 + 13 + 15) * 0.001
+        assert!((result.borrow_fees - 0.39).abs() < 1e-10);
+        let expected_cash = 1_000.0 + 10.0 * 11.0 - 0.39;
+        assert!((result.portfolio.cash - expected_cash).abs() < 1e-10);
+        assert!((result.equity.values[2] - (expected_cash - 150.0)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_cash_account_short_capped_to_equity() {
+        let config = short_config(ShortConfig::default());
+        let result = run(&sample(), &mut ShortOnce(1e9), &config);
+
+        // 1,000 of equity at 11 covers a short of 90 shares
+        assert_eq!(result.fills[0].qty, -90.0);
+        assert_eq!(
+            result.events[0].kind,
+            EventKind::OrderCapped {
+                requested: -1e9,
+                filled: -90.0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_hard_to_borrow_rejects_short() {
+        let config = short_config(ShortConfig {
+            hard_to_borrow: HashSet::from(["XYZ".to_string()]),
+            ..Default::default()
+        });
+        let result = run(&sample(), &mut ShortOnce(10.0), &config);
+
+        assert!(result.fills.is_empty());
+        assert_eq!(
+            result.events,
+            vec![Event {
+                date: sample()[0].date,
+                kind: EventKind::ShortRejected { qty: 10.0 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_recall_forces_buy_in_at_open() {
+        let candles = sample();
+        let config = short_config(ShortConfig {
+            recalls: HashMap::from([("XYZ".to_string(), candles[1].date)]),
+            ..Default::default()
+        });
+        let result = run(&candles, &mut ShortOnce(10.0), &config);
+
+        assert_eq!(position(&result), 0.0);
+        assert_eq!(result.fills[1].price, 12.0);
+        assert_eq!(
+            result.events[0].kind,
+            EventKind::BuyIn {
+                qty: 10.0,
+                price: 12.0,
+                reason: BuyInReason::Recall,
+            }
+        );
+    }
+
+    #[test]
+    fn test_loss_limit_forces_buy_in_at_close() {
+        let candles = sample();
+        // shorted at 11, limit trips at 11 * 1.3 = 14.3 -> day 3's close of 15
+        let config = short_config(ShortConfig {
+            max_short_loss: Some(0.3),
+            ..Default::default()
+        });
+        let result = run(&candles, &mut ShortOnce(10.0), &config);
+
+        assert_eq!(position(&result), 0.0);
+        assert_eq!(result.events.len(), 1);
+        assert_eq!(result.events[0].date, candles[2].date);
+        assert!(matches!(
+            result.events[0].kind,
+            EventKind::BuyIn {
+                reason: BuyInReason::LossLimit,
+                ..
+            }
+        ));
+    }
+
+    // Buys `qty` on the first bar, then holds
+    struct BuyOnce(f64);
+
+    impl Strategy for BuyOnce {
+        fn on_bar(&mut self, ctx: &Context) -> Signal {
+            if ctx.index() == 0 {
+                Signal::Buy(self.0)
+            } else {
+                Signal::Hold
+            }
+        }
+    }
+
+    fn margin_config(margin: MarginConfig) -> BacktestConfig {
+        BacktestConfig {
+            initial_cash: 1_000.0,
+            execution: Execution::SameClose,
+            margin: Some(margin),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_margin_caps_leverage() {
+        let config = margin_config(MarginConfig::default());
+        let result = run(&sample(), &mut BuyOnce(1_000.0), &config);
+
+        // 2x of 1,000 equity at 11.0 -> 181 shares
+        assert_eq!(position(&result), 181.0);
+        assert_eq!(
+            result.events[0].kind,
+            EventKind::OrderCapped {
+                requested: 1_000.0,
+                filled: 181.0,
+            }
+        );
+        assert!(result.fills[0].qty * 11.0 > 1_000.0);
+    }
+
+    #[test]
+    fn test_debit_interest_uses_risk_free_plus_spread() {
+        let config = BacktestConfig {
+            risk_free: vec![0.001],
+            ..margin_config(MarginConfig {
+                debit_spread: 0.252,
+                ..Default::default()
+            })
+        };
+        let result = run(&sample()[..1], &mut BuyOnce(100.0), &config);
+
+        // debit of 100; 0.1% risk-free + 0.1% spread for one day
+        assert!((result.debit_interest - 0.2).abs() < 1e-10);
+        assert!((result.portfolio.cash - (-100.0 - 0.2)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_margin_call_liquidates_to_initial_margin() {
+        let candles = vec![candle(1, 10.0, 10.0), candle(2, 7.0, 7.0)];
+        let config = margin_config(MarginConfig {
+            debit_spread: 0.0,
+            debit_base_rate: 0.0,
+            ..Default::default()
+        });
+        let result = run(&candles, &mut BuyOnce(200.0), &config);
+
+        // equity 1000 - 200 * 3 = 400 vs requirement 200 * 7 * 0.25 = 350: no call
+        assert!(result.events.is_empty());
+
+        let candles = vec![candle(1, 10.0, 10.0), candle(2, 6.5, 6.5)];
+        let result = run(&candles, &mut BuyOnce(200.0), &config);
+
+        // equity 300 vs requirement 325 -> keep floor(300 * 2 / 6.5) = 92 shares
+        assert_eq!(position(&result), 92.0);
+        assert_eq!(
+            result.events,
+            vec![Event {
+                date: candles[1].date,
+                kind: EventKind::MarginCall {
+                    equity: 300.0,
+                    requirement: 325.0,
+                    qty: -108.0,
+                    price: 6.5,
+                },
+            }]
+        );
+        // liquidation at the close leaves equity unchanged
+        assert!((result.equity.values[1] - 300.0).abs() < 1e-10);
+    }
+
+    struct Idle;
+
+    impl Strategy for Idle {
+        fn on_bar(&mut self, _ctx: &Context) -> Signal {
+            Signal::Hold
+        }
+    }
+
+    #[test]
+    fn test_idle_cash_earns_risk_free() {
+        let config = BacktestConfig {
+            initial_cash: 1_000.0,
+            cash_interest: Some(CashInterest::default()),
+            risk_free: vec![0.001, 0.002],
+            ..Default::default()
+        };
+        let result = run(&sample(), &mut Idle, &config);
+
+        // the last bar reuses the final risk-free value
+        let expected = 1_000.0 * 1.001 * 1.002 * 1.002;
+        assert!((result.equity.values[2] - expected).abs() < 1e-9);
+        assert!((result.cash_interest - (expected - 1_000.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cash_interest_haircut() {
+        let config = BacktestConfig {
+            initial_cash: 1_000.0,
+            cash_interest: Some(CashInterest { haircut: 0.25 }),
+            risk_free: vec![0.002],
+            ..Default::default()
+        };
+        let result = run(&sample()[..1], &mut Idle, &config);
+        assert!((result.cash_interest - 1.5).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_short_proceeds_do_not_earn_interest() {
+        let config = BacktestConfig {
+            cash_interest: Some(CashInterest::default()),
+            risk_free: vec![0.001],
+            ..short_config(ShortConfig {
+                default_borrow_rate: 0.0,
+                ..Default::default()
+            })
+        };
+        let result = run(&sample()[..1], &mut ShortOnce(10.0), &config);
+
+        // only the original 1,000 is idle; the 110 of proceeds is not
+        assert!((result.cash_interest - 1.0).abs() < 1e-10);
+    }
+
+    // Buys 10 @ 10 then 10 @ 20, sells 15 @ 30
+    fn ledger(method: LotMethod) -> Portfolio {
+        let mut p = Portfolio::new(1_000.0, method);
+        p.apply_fill("XYZ", 10.0, 10.0, day(1));
+        p.apply_fill("XYZ", 10.0, 20.0, day(2));
+        p.apply_fill("XYZ", -15.0, 30.0, day(3));
+        p
+    }
+
+    #[test]
+    fn test_portfolio_fifo() {
+        let p = ledger(LotMethod::Fifo);
+        let pos = p.position("XYZ").unwrap();
+
+        // 10 * (30 - 10) + 5 * (30 - 20)
+        assert_eq!(pos.realized_pnl, 250.0);
+        assert_eq!(pos.quantity(), 5.0);
+        assert_eq!(pos.avg_cost(), 20.0);
+        assert_eq!(pos.unrealized_pnl(), 50.0);
+        assert_eq!(p.cash, 1_000.0 - 100.0 - 200.0 + 450.0);
+    }
+
+    #[test]
+    fn test_portfolio_lifo() {
+        let p = ledger(LotMethod::Lifo);
+        let pos = p.position("XYZ").unwrap();
+
+        // 10 * (30 - 20) + 5 * (30 - 10)
+        assert_eq!(pos.realized_pnl, 200.0);
+        assert_eq!(pos.avg_cost(), 10.0);
+        assert_eq!(pos.lots().next().unwrap().date, day(1));
+    }
+
+    #[test]
+    fn test_portfolio_average_cost() {
+        let p = ledger(LotMethod::AverageCost);
+        let pos = p.position("XYZ").unwrap();
+
+        // 15 * (30 - 15)
+        assert_eq!(pos.realized_pnl, 225.0);
+        assert_eq!(pos.avg_cost(), 15.0);
+        assert_eq!(pos.lots().count(), 1);
+    }
+
+    #[test]
+    fn test_portfolio_flip_to_short() {
+        let mut p = Portfolio::new(0.0, LotMethod::Fifo);
+        p.apply_fill("XYZ", 10.0, 10.0, day(1));
+        let realized = p.apply_fill("XYZ", -15.0, 12.0, day(2));
+        p.mark("XYZ", 11.0);
+
+        assert_eq!(realized, 20.0);
+        assert_eq!(p.quantity("XYZ"), -5.0);
+        assert_eq!(p.position("XYZ").unwrap().avg_cost(), 12.0);
+        assert_eq!(p.unrealized_pnl(), 5.0);
+        assert_eq!(p.market_value(), -55.0);
+        assert_eq!(p.gross_exposure(), 55.0);
+        assert_eq!(p.equity(), p.realized_pnl() + p.unrealized_pnl());
+    }
+
+    #[test]
+    fn test_portfolio_multiple_symbols() {
+        let mut p = Portfolio::new(1_000.0, LotMethod::Fifo);
+        p.apply_fill("AAA", 10.0, 10.0, day(1));
+        p.apply_fill("BBB", -5.0, 20.0, day(1));
+        p.mark("AAA", 12.0);
+        p.mark("BBB", 18.0);
+
+        assert_eq!(p.market_value(), 120.0 - 90.0);
+        assert_eq!(p.gross_exposure(), 210.0);
+        assert_eq!(p.equity(), 1_000.0 + 20.0 + 10.0);
+    }
+
+    #[test]
+    fn test_equity_curve_feeds_metrics() {
+        let candles = sample();
+        let config = BacktestConfig {
+            initial_cash: 1_100.0,
+            execution: Execution::SameClose,
+            ..Default::default()
+        };
+        let result = run(&candles, &mut BuyAndHold::default(), &config);
+
+        // fully invested from the first close, so the account tracks the closes
+        assert_eq!(result.equity.len(), candles.len());
+        let returns = result.equity.returns();
+        let expected = metrics::daily_returns(&candles);
+        for (r, e) in returns.iter().zip(expected.values()) {
+            assert!((r - e).abs() < 1e-10);
+        }
+        assert!(metrics::calc_stats(&returns).is_some());
+    }
+
+    // Buys on the first bar, sells everything on the third
+    struct RoundTrip;
+
+    impl Strategy for RoundTrip {
+        fn on_bar(&mut self, ctx: &Context) -> Signal {
+            match ctx.index() {
+                0 => Signal::Buy(10.0),
+                2 => Signal::Sell(ctx.position()),
+                _ => Signal::Hold,
+            }
+        }
+    }
+
+    #[test]
+    fn test_round_trip_trade_record() {
+        let candles = vec![
+            candle(1, 10.0, 10.0),
+            candle(2, 12.0, 9.0),
+            candle(3, 11.0, 14.0),
+        ];
+        let config = BacktestConfig {
+            execution: Execution::SameClose,
+            commission: 0.01,
+            ..Default::default()
+        };
+        let result = run(&candles, &mut RoundTrip, &config);
+
+        assert_eq!(result.trades.len(), 1);
+        let trade = &result.trades[0];
+        assert_eq!(trade.entry_date, candles[0].date);
+        assert_eq!(trade.exit_date, candles[2].date);
+        assert_eq!(trade.entry_price, 10.0);
+        assert_eq!(trade.exit_price, 14.0);
+        assert_eq!(trade.qty, 10.0);
+        assert!((trade.fees - 2.4).abs() < 1e-10);
+        assert!((trade.pnl - (40.0 - 2.4)).abs() < 1e-10);
+        // day 2 ranges from 9 to 12, day 3 from 11 to 14 (before the exit)
+        assert_eq!(trade.mae, -10.0);
+        assert_eq!(trade.mfe, 40.0);
+        assert_eq!(trade.bars_held, 2);
+        assert_eq!(trade.holding_days(), 2);
+        assert!((result.commissions - 2.4).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_slippage_moves_fill_prices() {
+        let config = BacktestConfig {
+            execution: Execution::SameClose,
+            slippage: 0.01,
+            ..Default::default()
+        };
+        let result = run(&sample(), &mut RoundTrip, &config);
+
+        assert!((result.fills[0].price - 11.11).abs() < 1e-10);
+        assert!((result.fills[1].price - 14.85).abs() < 1e-10);
+        let trade = &result.trades[0];
+        assert!((trade.slippage - (1.1 + 1.5)).abs() < 1e-10);
+        assert!((trade.pnl - 10.0 * (14.85 - 11.11)).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_flip_closes_one_trade_and_opens_another() {
+        struct Flip;
+        impl Strategy for Flip {
+            fn on_bar(&mut self, ctx: &Context) -> Signal {
+                match ctx.index() {
+                    0 => Signal::Buy(10.0),
+                    1 => Signal::Sell(15.0),
+                    _ => Signal::Hold,
+                }
+            }
+        }
+
+        let config = BacktestConfig {
+            execution: Execution::SameClose,
+            shorting: Some(ShortConfig {
+                default_borrow_rate: 0.0,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let result = run(&sample(), &mut Flip, &config);
+
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].qty, 10.0);
+        assert_eq!(result.trades[0].pnl, 20.0);
+        assert_eq!(position(&result), -5.0);
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+    use crate::strategy::SmaCrossover;
+    use crate::test_util::day;
+    use statrs::statistics::Statistics;
+
+    fn trending() -> Vec<Candle> {
+        (0..120)
+            .map(|i| {
+                let base = 100.0 + i as f64 * 0.5 + 5.0 * (i as f64 / 6.0).sin();
+                Candle {
+                    date: day(i),
+                    open: base,
+                    high: base + 1.0,
+                    low: base - 1.0,
+                    close: base + 0.2,
+                    volume: 100.0,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grid_search_picks_best_trial() {
+        let candles = trending();
+        let params = [(2, 5), (3, 10), (5, 20), (10, 30)];
+        let report = grid_search(
+            &candles,
+            &params,
+            &BacktestConfig::default(),
+            |&(fast, slow)| SmaCrossover { fast, slow },
+        )
+        .unwrap();
+
+        assert_eq!(report.trials.len(), params.len());
+        let max = report
+            .trials
+            .iter()
+            .filter_map(|t| t.sharpe)
+            .fold(f64::MIN, f64::max);
+        assert_eq!(report.sharpe, max);
+        let winner = report
+            .trials
+            .iter()
+            .find(|t| t.sharpe == Some(max))
+            .unwrap();
+        assert_eq!(report.best, winner.params);
+
+        let psr = report.psr.unwrap();
+        let dsr = report.dsr.unwrap();
+        assert!((0.0..=1.0).contains(&psr));
+        assert!(dsr <= psr);
+    }
+
+    #[test]
+    fn test_grid_search_empty() {
+        let candles = trending();
+        let params: [(usize, usize); 0] = [];
+        let report = grid_search(
+            &candles,
+            &params,
+            &BacktestConfig::default(),
+            |&(fast, slow)| SmaCrossover { fast, slow },
+        );
+        assert!(report.is_none());
+    }
+
+    #[test]
+    fn test_grid_search_counts_trials_without_sharpe() {
+        let candles = trending();
+        // a slow window longer than the data never trades, so has no Sharpe
+        let params = [(2, 5), (3, 10), (100, 200)];
+        let report = grid_search(
+            &candles,
+            &params,
+            &BacktestConfig::default(),
+            |&(fast, slow)| SmaCrossover { fast, slow },
+        )
+        .unwrap();
+
+        assert_eq!(report.trials.len(), 3);
+        assert_eq!(report.trials[2].sharpe, None);
+        let sharpes: Vec<f64> = report.trials[..2]
+            .iter()
+            .map(|t| t.sharpe.unwrap())
+            .collect();
+        let returns = Returns::new(ReturnKind::Simple, report.result.equity.simple_returns());
+        let threshold = metrics::expected_max_sharpe(3, sharpes.variance()).unwrap();
+        let expected = metrics::probabilistic_sharpe_ratio(&returns, threshold).unwrap();
+        assert!((report.dsr.unwrap() - expected).abs() < 1e-12);
+    }
+}
+
+#[cfg(test)]
+mod rebalance_tests {
+    use super::*;
+    use crate::optimize::{Constraints, Objective};
+    use crate::strategy::{Rebalance, Schedule, Targets};
+    use crate::test_util::day;
+    use std::collections::BTreeSet;
+
+    fn series(closes: impl Iterator<Item = f64>) -> Vec<Candle> {
+        closes
+            .enumerate()
+            .map(|(i, close)| Candle {
+                date: day(i as u64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 1_000.0,
+            })
+            .collect()
+    }
+
+    // A stock that rises 0.5% a day and a bond that doesn't move, over
+    // January through March 2025
+    fn universe() -> BTreeMap<String, Vec<Candle>> {
+        BTreeMap::from([
+            (
+                "STK".to_string(),
+                series((0..90).map(|i| 100.0 * 1.005f64.powi(i))),
+            ),
+            ("BND".to_string(), series((0..90).map(|_| 50.0))),
+        ])
+    }
+
+    fn sixty_forty(schedule: Schedule) -> Rebalance {
+        let targets = vec![("STK".to_string(), 0.6), ("BND".to_string(), 0.4)];
+        Rebalance::new(Targets::Fixed(targets), schedule)
+    }
+
+    fn same_close() -> BacktestConfig {
+        BacktestConfig {
+            execution: Execution::SameClose,
+            ..Default::default()
+        }
+    }
+
+    fn weight(result: &BacktestResult, symbol: &str, bar: usize) -> f64 {
+        result.equity.symbol_value[symbol][bar] / result.equity.values[bar]
+    }
+
+    #[test]
+    fn test_monthly_sixty_forty() {
+        let result = run_portfolio(
+            &universe(),
+            &mut sixty_forty(Schedule::Monthly),
+            &same_close(),
+        );
+        assert_eq!(result.equity.len(), 90);
+
+        // trades on the first bar and the first bar of February and March
+        let dates: BTreeSet<NaiveDate> = result.fills.iter().map(|f| f.date).collect();
+        assert_eq!(dates, BTreeSet::from([day(0), day(31), day(59)]));
+        // within a share of target
+        for bar in [0, 31, 59] {
+            assert!((weight(&result, "STK", bar) - 0.6).abs() < 0.002);
+            assert!((weight(&result, "BND", bar) - 0.4).abs() < 0.002);
+        }
+        // the stock drifts above target between rebalances
+        assert!(weight(&result, "STK", 30) > 0.63);
+        // sells come before buys so they fund them
+        let february: Vec<&Fill> = result.fills.iter().filter(|f| f.date == day(31)).collect();
+        assert!(february[0].qty < 0.0 && february[0].symbol == "STK");
+        assert!(february[1].qty > 0.0 && february[1].symbol == "BND");
+    }
+
+    #[test]
+    fn test_drift_band_trades_only_outside_band() {
+        let mut strategy = sixty_forty(Schedule::Drift(0.05));
+        let result = run_portfolio(&universe(), &mut strategy, &same_close());
+        let dates: BTreeSet<NaiveDate> = result.fills.iter().map(|f| f.date).collect();
+        assert!(dates.len() > 2);
+        for bar in 0..result.equity.len() {
+            assert!((weight(&result, "STK", bar) - 0.6).abs() <= 0.05 + 0.001);
+        }
+        // far fewer trading days than daily rebalancing
+        let daily = run_portfolio(
+            &universe(),
+            &mut sixty_forty(Schedule::Daily),
+            &same_close(),
+        );
+        let daily_dates: BTreeSet<NaiveDate> = daily.fills.iter().map(|f| f.date).collect();
+        assert!(dates.len() * 3 < daily_dates.len());
+    }
+
+    #[test]
+    fn test_lot_sizes_and_next_open() {
+        let mut strategy = sixty_forty(Schedule::Quarterly);
+        strategy.lot_sizes.insert("STK".to_string(), 100.0);
+        strategy.lot_sizes.insert("BND".to_string(), 50.0);
+        let result = run_portfolio(&universe(), &mut strategy, &BacktestConfig::default());
+
+        assert!(!result.fills.is_empty());
+        for fill in &result.fills {
+            // signalled on the first bar, filled at the next open
+            assert_eq!(fill.date, day(1));
+            let lot = if fill.symbol == "STK" { 100.0 } else { 50.0 };
+            assert_eq!(fill.qty % lot, 0.0);
+        }
+        assert!(result.portfolio.cash >= 0.0);
+    }
+
+    #[test]
+    fn test_optimized_targets() {
+        // same drift, but the second asset swings four times as much, so
+        // equal risk puts more capital in the first
+        let calm = series((0..90).map(|i| 100.0 * (1.0 + 0.01 * (i % 2) as f64)));
+        let wild = series((0..90).map(|i| 100.0 * (1.0 + 0.04 * (i % 2) as f64)));
+        let candles = BTreeMap::from([("CALM".to_string(), calm), ("WILD".to_string(), wild)]);
+        let mut strategy = Rebalance::new(
+            Targets::Optimized {
+                objective: Objective::EqualRiskContribution,
+                constraints: Constraints::default(),
+                lookback: 20,
+            },
+            Schedule::Monthly,
+        );
+        let result = run_portfolio(&candles, &mut strategy, &same_close());
+
+        // nothing to estimate from until 20 returns exist, so January's
+        // rebalance waits for them instead of for February
+        let dates: BTreeSet<NaiveDate> = result.fills.iter().map(|f| f.date).collect();
+        assert_eq!(dates.first(), Some(&day(20)));
+        assert!(dates.contains(&day(31)));
+        let calm_value = result.equity.symbol_value["CALM"][20];
+        let wild_value = result.equity.symbol_value["WILD"][20];
+        assert!(calm_value > 3.0 * wild_value);
+    }
+
+    #[test]
+    fn test_drift_keeps_optimized_weights_between_rebalances() {
+        // the two assets swap volatility halfway through; re-estimating every
+        // bar would chase the swap, but the holdings never leave the band
+        // around the weights set on the first rebalance
+        let swing = |calm: bool| {
+            series((0..90).map(move |i| {
+                let size = if (i < 45) == calm { 0.01 } else { 0.04 };
+                100.0 * (1.0 + size * (i % 2) as f64)
+            }))
+        };
+        let candles = BTreeMap::from([
+            ("CALM".to_string(), swing(true)),
+            ("WILD".to_string(), swing(false)),
+        ]);
+        let mut strategy = Rebalance::new(
+            Targets::Optimized {
+                objective: Objective::EqualRiskContribution,
+                constraints: Constraints::default(),
+                lookback: 20,
+            },
+            Schedule::Drift(0.05),
+        );
+        let result = run_portfolio(&candles, &mut strategy, &same_close());
+
+        let dates: BTreeSet<NaiveDate> = result.fills.iter().map(|f| f.date).collect();
+        assert_eq!(dates, BTreeSet::from([day(20)]));
+        assert!(weight(&result, "CALM", 89) > 0.7);
+    }
+
+    #[test]
+    fn test_buy_without_cash_does_not_sell() {
+        let mut portfolio = Portfolio::new(1_000.0, LotMethod::Fifo);
+        portfolio.apply_fill("A", 10.0, 100.0, day(0));
+        portfolio.cash = -50.0;
+        let order = Order {
+            symbol: "A".to_string(),
+            qty: 5.0,
+            lot: 1.0,
+        };
+
+        let fill = fill_order(&mut portfolio, &order, 100.0, day(1), &same_close());
+        assert!(fill.is_none());
+        assert_eq!(portfolio.quantity("A"), 10.0);
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::collections::BTreeMap;
+
+//
+// --------------------
+// Calendar Returns
+// --------------------
+// Inputs are daily simple returns with `dates[i]` the date of `returns[i]`.
+// Periods are compounded, so a month's figure is the growth over that month.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CalendarTable {
+    /// Compounded return per (year, month), months numbered 1-12
+    pub months: BTreeMap<(i32, u32), f64>,
+    /// Compounded return per calendar year (partial years included)
+    pub years: BTreeMap<i32, f64>,
+}
+
+impl CalendarTable {
+    pub fn month(&self, year: i32, month: u32) -> Option<f64> {
+        self.months.get(&(year, month)).copied()
+    }
+
+    pub fn year(&self, year: i32) -> Option<f64> {
+        self.years.get(&year).copied()
+    }
+}
+
+// Month-by-year grid plus yearly totals
+pub fn calendar_table(dates: &[NaiveDate], returns: &[f64]) -> Option<CalendarTable> {
+    if dates.len() != returns.len() || dates.is_empty() {
+        return None;
+    }
+    let mut growth_m: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+    let mut growth_y: BTreeMap<i32, f64> = BTreeMap::new();
+    for (d, r) in dates.iter().zip(returns) {
+        *growth_m.entry((d.year(), d.month())).or_insert(1.0) *= 1.0 + r;
+        *growth_y.entry(d.year()).or_insert(1.0) *= 1.0 + r;
+    }
+    Some(CalendarTable {
+        months: growth_m.into_iter().map(|(k, g)| (k, g - 1.0)).collect(),
+        years: growth_y.into_iter().map(|(k, g)| (k, g - 1.0)).collect(),
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnnualComparison {
+    pub year: i32,
+    pub strategy: f64,
+    pub benchmark: f64,
+    /// Strategy minus benchmark
+    pub excess: f64,
+}
+
+// Yearly returns of both series, for the years both have data. The two
+// series may have different trading days.
+pub fn annual_vs_benchmark(
+    dates: &[NaiveDate],
+    returns: &[f64],
+    bench_dates: &[NaiveDate],
+    bench_rets: &[f64],
+) -> Option<Vec<AnnualComparison>> {
+    let strategy = calendar_table(dates, returns)?;
+    let bench = calendar_table(bench_dates, bench_rets)?;
+    Some(
+        strategy
+            .years
+            .iter()
+            .filter_map(|(&year, &s)| {
+                let b = bench.year(year)?;
+                Some(AnnualComparison {
+                    year,
+                    strategy: s,
+                    benchmark: b,
+                    excess: s - b,
+                })
+            })
+            .collect(),
+    )
+}
+
+//
+// --------------------
+// Seasonality
+// --------------------
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeasonalStats {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Share of periods with a positive return
+    pub hit_rate: f64,
+}
+
+fn seasonal_stats(values: &[f64]) -> SeasonalStats {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let std_dev = if values.len() > 1 {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+    } else {
+        0.0
+    };
+    SeasonalStats {
+        count: values.len(),
+        mean,
+        std_dev,
+        hit_rate: values.iter().filter(|v| **v > 0.0).count() as f64 / n,
+    }
+}
+
+// Daily return statistics per weekday, Monday first; days without data are
+// left out
+pub fn day_of_week(dates: &[NaiveDate], returns: &[f64]) -> Option<Vec<(Weekday, SeasonalStats)>> {
+    if dates.len() != returns.len() {
+        return None;
+    }
+    let mut by_day: BTreeMap<u32, Vec<f64>> = BTreeMap::new();
+    for (d, r) in dates.iter().zip(returns) {
+        by_day
+            .entry(d.weekday().num_days_from_monday())
+            .or_default()
+            .push(*r);
+    }
+    Some(
+        by_day
+            .into_iter()
+            .map(|(day, values)| (WEEKDAYS[day as usize], seasonal_stats(&values)))
+            .collect(),
+    )
+}
+
+// Statistics of the compounded monthly returns per calendar month (1-12)
+pub fn month_of_year(dates: &[NaiveDate], returns: &[f64]) -> Option<BTreeMap<u32, SeasonalStats>> {
+    let table = calendar_table(dates, returns)?;
+    let mut by_month: BTreeMap<u32, Vec<f64>> = BTreeMap::new();
+    for (&(_, month), &r) in &table.months {
+        by_month.entry(month).or_default().push(r);
+    }
+    Some(
+        by_month
+            .into_iter()
+            .map(|(m, values)| (m, seasonal_stats(&values)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_calendar_table_compounds() {
+        let dates = [
+            date(2024, 12, 30),
+            date(2024, 12, 31),
+            date(2025, 1, 2),
+            date(2025, 1, 3),
+            date(2025, 2, 3),
+        ];
+        let rets = [0.1, 0.1, -0.1, 0.2, 0.05];
+        let table = calendar_table(&dates, &rets).unwrap();
+
+        assert!((table.month(2024, 12).unwrap() - 0.21).abs() < 1e-12);
+        assert!((table.month(2025, 1).unwrap() - (0.9 * 1.2 - 1.0)).abs() < 1e-12);
+        assert!((table.year(2025).unwrap() - (0.9 * 1.2 * 1.05 - 1.0)).abs() < 1e-12);
+        assert_eq!(table.month(2025, 3), None);
+        assert!(calendar_table(&dates[1..], &rets).is_none());
+    }
+
+    #[test]
+    fn test_annual_vs_benchmark() {
+        let dates = [date(2024, 6, 3), date(2025, 6, 2), date(2025, 6, 3)];
+        let rets = [0.1, 0.05, 0.05];
+        // the benchmark trades on other days and has no 2024 data
+        let bench_dates = [date(2025, 1, 2), date(2025, 7, 1)];
+        let bench = [0.02, 0.03];
+
+        let rows = annual_vs_benchmark(&dates, &rets, &bench_dates, &bench).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].year, 2025);
+        assert!((rows[0].strategy - 0.1025).abs() < 1e-12);
+        assert!((rows[0].benchmark - 0.0506).abs() < 1e-12);
+        assert!((rows[0].excess - 0.0519).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_day_of_week() {
+        // 2025-09-01 is a Monday
+        let dates: Vec<NaiveDate> = (1..=12).map(|d| date(2025, 9, d)).collect();
+        let rets: Vec<f64> = dates
+            .iter()
+            .map(|d| {
+                if d.weekday() == Weekday::Mon {
+                    0.01
+                } else {
+                    -0.001
+                }
+            })
+            .collect();
+        let stats = day_of_week(&dates, &rets).unwrap();
+
+        assert_eq!(stats.len(), 7);
+        assert_eq!(stats[0].0, Weekday::Mon);
+        assert_eq!(stats[0].1.count, 2);
+        assert!((stats[0].1.mean - 0.01).abs() < 1e-12);
+        assert_eq!(stats[0].1.hit_rate, 1.0);
+        assert_eq!(stats[1].1.hit_rate, 0.0);
+    }
+
+    #[test]
+    fn test_month_of_year() {
+        let dates = [date(2023, 1, 3), date(2024, 1, 2), date(2024, 2, 1)];
+        let rets = [0.02, -0.01, 0.03];
+        let stats = month_of_year(&dates, &rets).unwrap();
+
+        let jan = stats[&1];
+        assert_eq!(jan.count, 2);
+        assert!((jan.mean - 0.005).abs() < 1e-12);
+        assert_eq!(jan.hit_rate, 0.5);
+        assert_eq!(stats[&2].count, 1);
+        assert_eq!(stats[&2].std_dev, 0.0);
+    }
+}
@@ -0,0 +1,540 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+//
+// --------------------
+// Universe
+// --------------------
+// Returns of several assets on the dates they all share; `returns[i][t]` is
+// asset i's simple return on `dates[t]`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Universe {
+    pub symbols: Vec<String>,
+    pub dates: Vec<NaiveDate>,
+    pub returns: Vec<Vec<f64>>,
+}
+
+impl Universe {
+    /// Keeps only the dates present in every series, in date order
+    pub fn align(series: &[(&str, &BTreeMap<NaiveDate, f64>)]) -> Universe {
+        let dates: Vec<NaiveDate> = match series.first() {
+            Some((_, first)) => first
+                .keys()
+                .filter(|d| series.iter().all(|(_, s)| s.contains_key(d)))
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        };
+        Universe {
+            symbols: series.iter().map(|(s, _)| s.to_string()).collect(),
+            returns: series
+                .iter()
+                .map(|(_, s)| dates.iter().map(|d| s[d]).collect())
+                .collect(),
+            dates,
+        }
+    }
+
+    pub fn assets(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn observations(&self) -> usize {
+        self.dates.len()
+    }
+
+    /// Average return per asset, a naive expected-return estimate
+    pub fn mean_returns(&self) -> Vec<f64> {
+        self.returns
+            .iter()
+            .map(|r| r.iter().sum::<f64>() / r.len() as f64)
+            .collect()
+    }
+
+    // Returns minus each asset's mean
+    fn demeaned(&self) -> Vec<Vec<f64>> {
+        self.returns
+            .iter()
+            .zip(self.mean_returns())
+            .map(|(r, m)| r.iter().map(|x| x - m).collect())
+            .collect()
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.symbols.is_empty()
+            && self.returns.len() == self.symbols.len()
+            && self.returns.iter().all(|r| r.len() == self.dates.len())
+    }
+}
+
+//
+// --------------------
+// Covariance Matrix
+// --------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct CovarianceMatrix {
+    pub symbols: Vec<String>,
+    /// Daily covariances, `values[i][j]` between symbols i and j
+    pub values: Vec<Vec<f64>>,
+}
+
+impl CovarianceMatrix {
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|s| s == a)?;
+        let j = self.symbols.iter().position(|s| s == b)?;
+        Some(self.values[i][j])
+    }
+
+    /// Daily volatility of each asset
+    pub fn volatilities(&self) -> Vec<f64> {
+        (0..self.len()).map(|i| self.values[i][i].sqrt()).collect()
+    }
+
+    /// Assets with zero variance get zero correlation with everything else
+    pub fn correlation(&self) -> CorrelationMatrix {
+        let vols = self.volatilities();
+        let values = (0..self.len())
+            .map(|i| {
+                (0..self.len())
+                    .map(|j| match (i == j, vols[i] * vols[j]) {
+                        (true, _) => 1.0,
+                        (false, denom) if denom > 0.0 => self.values[i][j] / denom,
+                        _ => 0.0,
+                    })
+                    .collect()
+            })
+            .collect();
+        CorrelationMatrix {
+            symbols: self.symbols.clone(),
+            values,
+        }
+    }
+
+    /// w' Σ w
+    pub fn portfolio_variance(&self, weights: &[f64]) -> Option<f64> {
+        if weights.len() != self.len() {
+            return None;
+        }
+        let mut var = 0.0;
+        for (i, wi) in weights.iter().enumerate() {
+            for (j, wj) in weights.iter().enumerate() {
+                var += wi * wj * self.values[i][j];
+            }
+        }
+        Some(var)
+    }
+
+    /// Weighted average volatility over portfolio volatility; 1.0 for a
+    /// single asset or perfectly correlated holdings, higher when they
+    /// diversify each other
+    pub fn diversification_ratio(&self, weights: &[f64]) -> Option<f64> {
+        let portfolio_vol = self.portfolio_variance(weights)?.sqrt();
+        if portfolio_vol == 0.0 {
+            return None;
+        }
+        let weighted: f64 = weights
+            .iter()
+            .zip(self.volatilities())
+            .map(|(w, v)| w.abs() * v)
+            .sum();
+        Some(weighted / portfolio_vol)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationMatrix {
+    pub symbols: Vec<String>,
+    pub values: Vec<Vec<f64>>,
+}
+
+impl CorrelationMatrix {
+    pub fn get(&self, a: &str, b: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|s| s == a)?;
+        let j = self.symbols.iter().position(|s| s == b)?;
+        Some(self.values[i][j])
+    }
+
+    /// Mean of the off-diagonal correlations
+    pub fn average(&self) -> Option<f64> {
+        let n = self.symbols.len();
+        if n < 2 {
+            return None;
+        }
+        let sum: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| self.values[i][j])
+            .sum();
+        Some(sum / (n * (n - 1)) as f64)
+    }
+
+    /// sqrt((1 - rho) / 2): 0 for perfectly correlated assets, 1 for
+    /// perfectly anti-correlated ones
+    pub fn distances(&self) -> Vec<Vec<f64>> {
+        self.values
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|rho| ((1.0 - rho) / 2.0).max(0.0).sqrt())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+//
+// --------------------
+// Estimators
+// --------------------
+// Unbiased sample covariance (n - 1 denominator)
+pub fn sample_covariance(universe: &Universe) -> Option<CovarianceMatrix> {
+    let n = universe.observations();
+    if !universe.is_valid() || n < 2 {
+        return None;
+    }
+    let x = universe.demeaned();
+    Some(CovarianceMatrix {
+        symbols: universe.symbols.clone(),
+        values: cross_products(&x, |_| 1.0 / (n - 1) as f64),
+    })
+}
+
+// RiskMetrics-style: zero-mean, with the weight of each day decaying by
+// `lambda` per day into the past (0.94 for daily data). Reacts to regime
+// changes much faster than the sample estimate.
+pub fn ewma_covariance(universe: &Universe, lambda: f64) -> Option<CovarianceMatrix> {
+    let n = universe.observations();
+    if !universe.is_valid() || n == 0 || !(0.0..1.0).contains(&lambda) {
+        return None;
+    }
+    let total: f64 = (0..n).map(|t| lambda.powi((n - 1 - t) as i32)).sum();
+    Some(CovarianceMatrix {
+        symbols: universe.symbols.clone(),
+        values: cross_products(&universe.returns, |t| {
+            lambda.powi((n - 1 - t) as i32) / total
+        }),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shrinkage {
+    pub covariance: CovarianceMatrix,
+    /// Weight on the target, between 0 (sample) and 1 (target only)
+    pub intensity: f64,
+}
+
+// Ledoit–Wolf (2004) shrinkage of the sample covariance toward a scaled
+// identity, with the intensity that minimizes expected squared error. Stays
+// well-conditioned (invertible) even with more assets than observations.
+pub fn ledoit_wolf(universe: &Universe) -> Option<Shrinkage> {
+    let n = universe.observations();
+    let p = universe.assets();
+    if !universe.is_valid() || n < 2 {
+        return None;
+    }
+    let x = universe.demeaned();
+    // the estimator is built on the biased (1 / n) sample covariance
+    let s = cross_products(&x, |_| 1.0 / n as f64);
+    let mu = (0..p).map(|i| s[i][i]).sum::<f64>() / p as f64;
+
+    let mut d2 = 0.0;
+    let mut b2 = 0.0;
+    for i in 0..p {
+        for j in 0..p {
+            let target = if i == j { mu } else { 0.0 };
+            d2 += (s[i][j] - target).powi(2);
+            b2 += (0..n)
+                .map(|t| (x[i][t] * x[j][t] - s[i][j]).powi(2))
+                .sum::<f64>();
+        }
+    }
+    b2 /= (n * n) as f64;
+
+    let intensity = if d2 > 0.0 { b2.min(d2) / d2 } else { 1.0 };
+    let values = (0..p)
+        .map(|i| {
+            (0..p)
+                .map(|j| {
+                    let target = if i == j { mu } else { 0.0 };
+                    intensity * target + (1.0 - intensity) * s[i][j]
+                })
+                .collect()
+        })
+        .collect();
+
+    Some(Shrinkage {
+        covariance: CovarianceMatrix {
+            symbols: universe.symbols.clone(),
+            values,
+        },
+        intensity,
+    })
+}
+
+// Σ_t weight(t) * x_i[t] * x_j[t] for every pair of series
+fn cross_products(x: &[Vec<f64>], weight: impl Fn(usize) -> f64) -> Vec<Vec<f64>> {
+    let p = x.len();
+    let mut out = vec![vec![0.0; p]; p];
+    for i in 0..p {
+        for j in i..p {
+            let v: f64 = x[i]
+                .iter()
+                .zip(&x[j])
+                .enumerate()
+                .map(|(t, (a, b))| weight(t) * a * b)
+                .sum();
+            out[i][j] = v;
+            out[j][i] = v;
+        }
+    }
+    out
+}
+
+//
+// --------------------
+// Hierarchical Clustering
+// --------------------
+// Agglomerative clustering on correlation distance. Leaves are numbered
+// 0..n in symbol order; merge k creates cluster n + k.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Linkage {
+    /// Nearest members (the usual choice for hierarchical risk parity)
+    #[default]
+    Single,
+    /// Farthest members
+    Complete,
+    /// Mean over all member pairs
+    Average,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Merge {
+    pub left: usize,
+    pub right: usize,
+    pub distance: f64,
+    /// Leaves under the new cluster
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dendrogram {
+    pub symbols: Vec<String>,
+    /// In merge order, `symbols.len() - 1` of them
+    pub merges: Vec<Merge>,
+}
+
+pub fn cluster(correlation: &CorrelationMatrix, linkage: Linkage) -> Option<Dendrogram> {
+    let n = correlation.symbols.len();
+    if n == 0 {
+        return None;
+    }
+    let leaf_distance = correlation.distances();
+    // live clusters as (id, leaves)
+    let mut live: Vec<(usize, Vec<usize>)> = (0..n).map(|i| (i, vec![i])).collect();
+    let mut merges = Vec::with_capacity(n - 1);
+
+    let between = |a: &[usize], b: &[usize]| {
+        let leaf_distance = &leaf_distance;
+        let pairs = a
+            .iter()
+            .flat_map(|i| b.iter().map(move |j| leaf_distance[*i][*j]));
+        match linkage {
+            Linkage::Single => pairs.fold(f64::INFINITY, f64::min),
+            Linkage::Complete => pairs.fold(0.0, f64::max),
+            Linkage::Average => pairs.sum::<f64>() / (a.len() * b.len()) as f64,
+        }
+    };
+
+    while live.len() > 1 {
+        let mut best = (0, 1, f64::INFINITY);
+        for a in 0..live.len() {
+            for b in a + 1..live.len() {
+                let d = between(&live[a].1, &live[b].1);
+                if d < best.2 {
+                    best = (a, b, d);
+                }
+            }
+        }
+        let (a, b, distance) = best;
+        let (right, right_leaves) = live.swap_remove(b);
+        let (left, mut leaves) = live.swap_remove(a);
+        leaves.extend(right_leaves);
+        merges.push(Merge {
+            left,
+            right,
+            distance,
+            size: leaves.len(),
+        });
+        live.push((n + merges.len() - 1, leaves));
+    }
+
+    Some(Dendrogram {
+        symbols: correlation.symbols.clone(),
+        merges,
+    })
+}
+
+impl Dendrogram {
+    /// Leaves ordered so that similar assets sit next to each other (the
+    /// quasi-diagonal order used by hierarchical risk parity)
+    pub fn order(&self) -> Vec<usize> {
+        let n = self.symbols.len();
+        let mut order = Vec::with_capacity(n);
+        let mut stack = vec![n + self.merges.len() - 1];
+        while let Some(id) = stack.pop() {
+            if id < n {
+                order.push(id);
+            } else {
+                let merge = &self.merges[id - n];
+                stack.push(merge.right);
+                stack.push(merge.left);
+            }
+        }
+        order
+    }
+
+    /// Cuts the tree into `k` groups of leaf indices (fewer if `k` exceeds
+    /// the number of leaves)
+    pub fn clusters(&self, k: usize) -> Vec<Vec<usize>> {
+        let n = self.symbols.len();
+        let k = k.clamp(1, n.max(1));
+        let mut groups: BTreeMap<usize, Vec<usize>> = (0..n).map(|i| (i, vec![i])).collect();
+        for (m, merge) in self.merges.iter().take(n - k).enumerate() {
+            let mut leaves = groups.remove(&merge.left).unwrap_or_default();
+            leaves.extend(groups.remove(&merge.right).unwrap_or_default());
+            groups.insert(n + m, leaves);
+        }
+        groups.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::day;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand_distr::{Distribution, Normal};
+
+    fn noise(n: usize, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let z = Normal::new(0.0, 0.01).unwrap();
+        (0..n).map(|_| z.sample(&mut rng)).collect()
+    }
+
+    // Two pairs of related assets: A/B share one driver, C/D another
+    fn universe() -> Universe {
+        let (x, y) = (noise(200, 1), noise(200, 2));
+        let (e1, e2, e3, e4) = (noise(200, 3), noise(200, 4), noise(200, 5), noise(200, 6));
+        let mix = |base: &[f64], e: &[f64]| -> Vec<f64> {
+            base.iter().zip(e).map(|(b, e)| b + 0.3 * e).collect()
+        };
+        Universe {
+            symbols: ["A", "C", "B", "D"].map(String::from).to_vec(),
+            dates: (0..200).map(day).collect(),
+            returns: vec![mix(&x, &e1), mix(&y, &e2), mix(&x, &e3), mix(&y, &e4)],
+        }
+    }
+
+    #[test]
+    fn test_align_keeps_common_dates() {
+        let a: BTreeMap<NaiveDate, f64> = (0..5).map(|i| (day(i), i as f64)).collect();
+        let b: BTreeMap<NaiveDate, f64> = (2..8).map(|i| (day(i), -(i as f64))).collect();
+        let u = Universe::align(&[("A", &a), ("B", &b)]);
+        assert_eq!(u.dates, vec![day(2), day(3), day(4)]);
+        assert_eq!(u.returns[0], vec![2.0, 3.0, 4.0]);
+        assert_eq!(u.returns[1], vec![-2.0, -3.0, -4.0]);
+    }
+
+    #[test]
+    fn test_sample_covariance_matches_pairwise() {
+        use statrs::statistics::Statistics;
+        let u = universe();
+        let cov = sample_covariance(&u).unwrap();
+        let pairwise = u.returns[0].as_slice().covariance(u.returns[2].as_slice());
+        assert!((cov.get("A", "B").unwrap() - pairwise).abs() < 1e-15);
+        assert_eq!(cov.values[1][3], cov.values[3][1]);
+
+        let corr = cov.correlation();
+        assert_eq!(corr.values[0][0], 1.0);
+        assert!(corr.get("A", "B").unwrap() > 0.8);
+        assert!(corr.get("A", "C").unwrap().abs() < 0.3);
+
+        // equal weights across four assets diversify
+        let ratio = cov.diversification_ratio(&[0.25; 4]).unwrap();
+        assert!(ratio > 1.0);
+        assert!(cov.portfolio_variance(&[1.0]).is_none());
+    }
+
+    #[test]
+    fn test_ewma_weights_recent_days() {
+        let dates: Vec<NaiveDate> = (0..100).map(day).collect();
+        // calm, then a volatile last stretch
+        let returns: Vec<f64> = (0..100)
+            .map(|t| if t < 90 { 0.001 } else { 0.03 } * if t % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let u = Universe {
+            symbols: vec!["A".to_string()],
+            dates,
+            returns: vec![returns],
+        };
+        let sample = sample_covariance(&u).unwrap().values[0][0];
+        let ewma = ewma_covariance(&u, 0.94).unwrap().values[0][0];
+        assert!(ewma > sample);
+        assert!(ewma_covariance(&u, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_ledoit_wolf_shrinks_toward_identity() {
+        let u = universe();
+        let sample = sample_covariance(&u).unwrap();
+        let lw = ledoit_wolf(&u).unwrap();
+        assert!(lw.intensity > 0.0 && lw.intensity < 1.0);
+        // off-diagonals pulled toward zero
+        assert!(lw.covariance.values[0][2].abs() < sample.values[0][2].abs());
+
+        // with fewer days than assets the sample matrix is singular; the
+        // shrunk one keeps a positive diagonal and a usable correlation
+        let short = Universe {
+            symbols: u.symbols.clone(),
+            dates: u.dates[..3].to_vec(),
+            returns: u.returns.iter().map(|r| r[..3].to_vec()).collect(),
+        };
+        let lw_short = ledoit_wolf(&short).unwrap();
+        assert!(lw_short.intensity > 0.0);
+        assert!(lw_short.covariance.volatilities().iter().all(|v| *v > 0.0));
+    }
+
+    #[test]
+    fn test_clustering_groups_correlated_assets() {
+        let corr = sample_covariance(&universe()).unwrap().correlation();
+        for linkage in [Linkage::Single, Linkage::Complete, Linkage::Average] {
+            let tree = cluster(&corr, linkage).unwrap();
+            assert_eq!(tree.merges.len(), 3);
+            assert_eq!(tree.merges[2].size, 4);
+            assert!(tree.merges[0].distance <= tree.merges[2].distance);
+
+            // symbols are A, C, B, D: pairs {0, 2} and {1, 3}
+            let mut groups = tree.clusters(2);
+            groups.iter_mut().for_each(|g| g.sort());
+            groups.sort();
+            assert_eq!(groups, vec![vec![0, 2], vec![1, 3]]);
+
+            let order = tree.order();
+            let position = |leaf| order.iter().position(|&x| x == leaf).unwrap();
+            assert_eq!(position(0).abs_diff(position(2)), 1);
+            assert_eq!(position(1).abs_diff(position(3)), 1);
+        }
+        assert_eq!(
+            cluster(&corr, Linkage::Single).unwrap().clusters(10).len(),
+            4
+        );
+    }
+}
@@ -27,22 +27,20 @@ mod date_format {
     use chrono::NaiveDate;
     use serde::{self, Deserialize, Deserializer};
 
-    const FORMATS: &[&str] = &["%Y-%m-%d", "%m/%d/%Y"];
+    const FORMATS: &[&str] = &["%Y-%m-%d", "%m/%d/%Y", "%Y%m%d"];
+
+    pub fn parse(s: &str) -> Option<NaiveDate> {
+        FORMATS
+            .iter()
+            .find_map(|fmt| NaiveDate::parse_from_str(s.trim(), fmt).ok())
+    }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
     where
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        for fmt in FORMATS {
-            if let Ok(date) = NaiveDate::parse_from_str(&s, fmt) {
-                return Ok(date);
-            }
-        }
-        Err(serde::de::Error::custom(format!(
-            "Invalid date format: {}",
-            s
-        )))
+        parse(&s).ok_or_else(|| serde::de::Error::custom(format!("Invalid date format: {}", s)))
     }
 }
 
@@ -111,6 +109,142 @@ pub fn load_risk_free_series<P: AsRef<Path>>(
     Ok(rf_returns)
 }
 
+/// Load dated yield levels (as decimals, 4.17% -> 0.0417) for one maturity
+/// column of a Treasury CSV, in file order.
+pub fn load_yield_series<P: AsRef<Path>>(
+    path: P,
+    maturity: &str,
+) -> Result<Vec<(NaiveDate, f64)>, Box<dyn Error>> {
+    let file = File::open(path)?;
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(file);
+    let mut yields = Vec::new();
+
+    for result in rdr.deserialize::<RiskFreeRateRow>() {
+        let row = result?;
+        if let Some(rate) = row.maturities.get(maturity).and_then(|rate| rate.0) {
+            yields.push((row.date, rate / 100.0));
+        }
+    }
+
+    Ok(yields)
+}
+
+/// Daily risk-free returns for each of `dates` from dated annual yields
+/// (e.g. `load_yield_series`), in any order. Each date takes the latest yield
+/// published on or before it; dates before the first yield get 0.0 rather
+/// than a rate not yet known. Empty if there are no yields.
+pub fn risk_free_for_dates(yields: &[(NaiveDate, f64)], dates: &[NaiveDate]) -> Vec<f64> {
+    let mut sorted = yields.to_vec();
+    sorted.sort_by_key(|(date, _)| *date);
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+
+    dates
+        .iter()
+        .map(|date| match sorted.partition_point(|(d, _)| d <= date) {
+            0 => 0.0,
+            published => (1.0 + sorted[published - 1].1).powf(1.0 / 252.0) - 1.0,
+        })
+        .collect()
+}
+
+//
+// --------------------
+// Factor Returns Loader
+// --------------------
+// Fama–French style file: a date column followed by one column per factor.
+// A column named "RF" is kept apart as the risk-free rate.
+#[derive(Debug, Clone, Default)]
+pub struct FactorData {
+    pub dates: Vec<NaiveDate>,
+    pub names: Vec<String>,
+    /// One series per entry in `names`
+    pub factors: Vec<Vec<f64>>,
+    pub risk_free: Option<Vec<f64>>,
+}
+
+impl FactorData {
+    /// Row index for each date
+    pub fn index(&self) -> HashMap<NaiveDate, usize> {
+        self.dates
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (*d, i))
+            .collect()
+    }
+}
+
+/// Load factor returns from any reader. With `percent` set, values such as
+/// the Kenneth French library's are divided by 100. Rows with a missing or
+/// unparseable value are skipped.
+pub fn load_factors_from_reader<R: Read>(
+    reader: R,
+    percent: bool,
+) -> Result<FactorData, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(reader);
+    let headers = rdr.headers()?.clone();
+    if headers.len() < 2 {
+        return Err("factor file needs a date column and at least one factor".into());
+    }
+
+    let rf_col = headers
+        .iter()
+        .skip(1)
+        .position(|h| h.eq_ignore_ascii_case("rf"));
+    let mut data = FactorData {
+        names: headers
+            .iter()
+            .skip(1)
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != rf_col)
+            .map(|(_, h)| h.to_string())
+            .collect(),
+        risk_free: rf_col.map(|_| Vec::new()),
+        ..Default::default()
+    };
+    data.factors = vec![Vec::new(); data.names.len()];
+    let scale = if percent { 0.01 } else { 1.0 };
+
+    for result in rdr.records() {
+        let record = result?;
+        let Some(date) = record.get(0).and_then(date_format::parse) else {
+            continue;
+        };
+        let values: Option<Vec<f64>> = record
+            .iter()
+            .skip(1)
+            .map(|v| v.parse::<f64>().ok().map(|v| v * scale))
+            .collect();
+        let Some(values) = values.filter(|v| v.len() == headers.len() - 1) else {
+            continue;
+        };
+
+        data.dates.push(date);
+        let mut factor = data.factors.iter_mut();
+        for (i, v) in values.into_iter().enumerate() {
+            match (Some(i) == rf_col, data.risk_free.as_mut()) {
+                (true, Some(rf)) => rf.push(v),
+                _ => factor.next().unwrap().push(v),
+            }
+        }
+    }
+
+    Ok(data)
+}
+
+/// Load factor returns from file path
+pub fn load_factors<P: AsRef<Path>>(path: P, percent: bool) -> Result<FactorData, Box<dyn Error>> {
+    load_factors_from_reader(File::open(path)?, percent)
+}
+
 // use chrono::NaiveDate;
 // use serde::Deserialize;
 // use std::error::Error;
@@ -242,3 +376,48 @@ pub fn load_risk_free_series<P: AsRef<Path>>(
 //         Err("Risk-free rate CSV is empty".into())
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ymd(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_risk_free_for_dates_fills_forward_only() {
+        // newest first, as in the Treasury files
+        let yields = vec![(ymd(2025, 1, 6), 0.05), (ymd(2025, 1, 2), 0.04)];
+        let dates = [
+            ymd(2025, 1, 1),
+            ymd(2025, 1, 2),
+            ymd(2025, 1, 3),
+            ymd(2025, 1, 6),
+            ymd(2025, 1, 7),
+        ];
+        let daily = |annual: f64| (1.0 + annual).powf(1.0 / 252.0) - 1.0;
+
+        let rf = risk_free_for_dates(&yields, &dates);
+        // nothing is published yet on the first date
+        let expected = [0.0, 0.04, 0.04, 0.05, 0.05].map(daily);
+        assert_eq!(rf.len(), dates.len());
+        for (got, want) in rf.iter().zip(expected) {
+            assert!((got - want).abs() < 1e-15);
+        }
+        assert!(risk_free_for_dates(&[], &dates).is_empty());
+    }
+
+    #[test]
+    fn test_factors_skip_short_trailer_rows() {
+        // Kenneth French files end with a one-column copyright line
+        let csv = "Date,Mkt-RF,SMB,RF\n\
+                   20250102,1.0,0.5,0.01\n\
+                   20250103,-0.5,0.2,0.01\n\
+                   \n\
+                   Copyright 2025 Kenneth R. French\n";
+        let factors = load_factors_from_reader(csv.as_bytes(), true).unwrap();
+        assert_eq!(factors.dates, vec![ymd(2025, 1, 2), ymd(2025, 1, 3)]);
+        assert_eq!(factors.factors[0], vec![0.01, -0.005]);
+    }
+}
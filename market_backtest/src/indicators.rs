@@ -0,0 +1,72 @@
+use crate::data::Candle;
+
+//
+// --------------------
+// Simple Moving Average
+// --------------------
+// Mean of the last `window` values, `None` until there are enough of them
+pub fn sma(values: &[f64], window: usize) -> Option<f64> {
+    if window == 0 || values.len() < window {
+        return None;
+    }
+    let tail = &values[values.len() - window..];
+    Some(tail.iter().sum::<f64>() / window as f64)
+}
+
+//
+// --------------------
+// Average True Range
+// --------------------
+// Simple average of the true range over the last `period` bars. Each true
+// range needs the previous close, so `period + 1` candles are required.
+pub fn atr(candles: &[Candle], period: usize) -> Option<f64> {
+    if period == 0 || candles.len() < period + 1 {
+        return None;
+    }
+    let ranges: Vec<f64> = candles[candles.len() - period - 1..]
+        .windows(2)
+        .map(|w| {
+            let (prev, bar) = (&w[0], &w[1]);
+            (bar.high - bar.low)
+                .max((bar.high - prev.close).abs())
+                .max((bar.low - prev.close).abs())
+        })
+        .collect();
+    sma(&ranges, period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sma() {
+        let values = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(sma(&values, 2), Some(3.5));
+        assert_eq!(sma(&values, 4), Some(2.5));
+        assert_eq!(sma(&values, 5), None);
+        assert_eq!(sma(&values, 0), None);
+    }
+
+    #[test]
+    fn test_atr_uses_gaps() {
+        let candle = |open: f64, high: f64, low: f64, close: f64| Candle {
+            date: chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume: 0.0,
+        };
+        let candles = [
+            candle(10.0, 11.0, 9.0, 10.0),
+            // range 2
+            candle(10.0, 11.0, 9.0, 10.0),
+            // gaps up: high - previous close = 5
+            candle(14.0, 15.0, 13.0, 14.0),
+        ];
+        assert_eq!(atr(&candles, 2), Some(3.5));
+        assert_eq!(atr(&candles, 1), Some(5.0));
+        assert_eq!(atr(&candles, 3), None);
+    }
+}
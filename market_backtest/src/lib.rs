@@ -1,5 +1,16 @@
+pub mod attribution;
 pub mod backtest;
+pub mod calendar;
+pub mod covariance;
 pub mod data;
 pub mod indicators;
 pub mod metrics;
+pub mod optimize;
+pub mod regression;
+pub mod scenario;
+pub mod sizing;
 pub mod strategy;
+pub mod volatility;
+
+#[cfg(test)]
+mod test_util;
@@ -1,7 +1,16 @@
 use std::path::PathBuf;
 
-use clap::Parser;
-use market_backtest::{data, metrics};
+use clap::{Parser, ValueEnum};
+use market_backtest::attribution;
+use market_backtest::backtest::{self, BacktestConfig, CashInterest, Execution};
+use market_backtest::metrics::{Resampling, ReturnKind, Returns, Simulation};
+use market_backtest::regression::{self, Regression, StdErrors};
+use market_backtest::scenario::{self, Scenario, ScenarioData};
+use market_backtest::strategy::{BuyAndHold, SmaCrossover};
+use market_backtest::volatility::{
+    Ewma, Garch, GarchForecast, Historical, RangeEstimator, RangeVolatility, VolatilityModel,
+};
+use market_backtest::{calendar, data, metrics};
 
 /// Command line interface
 #[derive(Parser, Debug)]
@@ -27,6 +36,89 @@ struct Args {
     /// Column in T-bill CSV to use (e.g. "1 Mo")
     #[arg(short = 'm', long, default_value = "1 Mo")]
     risk_free_maturity: String,
+
+    /// When a signal computed on a bar's close is filled
+    #[arg(short = 'e', long, value_enum, default_value_t = ExecutionArg::NextOpen)]
+    execution: ExecutionArg,
+
+    /// Fraction of the risk-free rate withheld from interest on idle cash
+    #[arg(long, default_value_t = 0.0)]
+    cash_haircut: f64,
+
+    /// How returns are resampled for the bootstrap confidence intervals
+    #[arg(long, value_enum, default_value_t = BootstrapArg::Stationary)]
+    bootstrap: BootstrapArg,
+
+    /// Block length (mean length for the stationary bootstrap)
+    #[arg(long, default_value_t = 10)]
+    block_size: usize,
+
+    /// Seed for Monte Carlo and bootstrap runs (random if omitted)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Also search SMA crossover windows and report the winner's deflated Sharpe
+    #[arg(long)]
+    optimize: bool,
+
+    /// Factor returns CSV (date column, one column per factor, optional RF)
+    #[arg(long)]
+    factors: Option<PathBuf>,
+
+    /// Factor file values are in percent (as in the Kenneth French library)
+    #[arg(long)]
+    factors_percent: bool,
+
+    /// Window length in bars for rolling metrics
+    #[arg(long, default_value_t = 63)]
+    rolling_window: usize,
+
+    /// Treasury column whose daily changes drive the rate stress scenario
+    #[arg(long, default_value = "10 yr")]
+    rate_maturity: String,
+
+    /// Returns each VaR forecast is fitted on when backtesting VaR
+    #[arg(long, default_value_t = 250)]
+    var_window: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BootstrapArg {
+    /// Independent daily draws
+    Iid,
+    /// Random-length blocks
+    Stationary,
+    /// Fixed-length blocks that wrap around
+    Circular,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ExecutionArg {
+    /// Fill at the next bar's open
+    NextOpen,
+    /// Fill at the signal bar's close (look-ahead, optimistic)
+    SameClose,
+}
+
+impl From<ExecutionArg> for Execution {
+    fn from(arg: ExecutionArg) -> Self {
+        match arg {
+            ExecutionArg::NextOpen => Execution::NextOpen,
+            ExecutionArg::SameClose => Execution::SameClose,
+        }
+    }
+}
+
+impl BootstrapArg {
+    fn resampling(self, block_size: usize) -> Resampling {
+        match self {
+            BootstrapArg::Iid => Resampling::Iid,
+            BootstrapArg::Stationary => Resampling::Stationary {
+                mean_block: block_size as f64,
+            },
+            BootstrapArg::Circular => Resampling::Circular { block: block_size },
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -36,26 +128,252 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let candles = data::load_csv(&args.file)?;
     let bench = data::load_csv(&args.benchmark)?;
 
-    let returns = metrics::daily_returns(&candles);
-    let bench_returns = metrics::daily_returns(&bench);
-
     // --- Load risk-free rates ---
-    let rf_daily: Vec<f64> = if let Some(rf_path) = &args.risk_free_file {
-        let series = data::load_risk_free_series(rf_path, &args.risk_free_maturity)?;
-        // Ensure length matches returns
-        if series.len() < returns.len() {
-            // pad with last value if needed
-            let mut padded = series.clone();
-            padded.resize(returns.len(), *series.last().unwrap());
-            padded
-        } else {
-            series[..returns.len()].to_vec()
+    // One daily rate per bar, matched to the candle dates; return i (bar i to
+    // i + 1) earns the rate accrued on bar i + 1.
+    let rf_bars: Vec<f64> = if let Some(rf_path) = &args.risk_free_file {
+        let yields = data::load_yield_series(rf_path, &args.risk_free_maturity)?;
+        if yields.is_empty() {
+            return Err(format!(
+                "no '{}' rates in {}",
+                args.risk_free_maturity,
+                rf_path.display()
+            )
+            .into());
         }
+        let candle_dates: Vec<_> = candles.iter().map(|c| c.date).collect();
+        data::risk_free_for_dates(&yields, &candle_dates)
     } else {
         // Fallback: convert CLI annual risk-free rate to daily
         let daily = (1.0 + args.risk_free).powf(1.0 / 252.0) - 1.0;
-        vec![daily; returns.len()]
+        vec![daily; candles.len()]
+    };
+    let rf_daily: Vec<f64> = rf_bars.iter().skip(1).copied().collect();
+
+    // --- Run backtest ---
+    let config = BacktestConfig {
+        execution: args.execution.into(),
+        cash_interest: Some(CashInterest {
+            haircut: args.cash_haircut,
+        }),
+        risk_free: rf_bars,
+        ..Default::default()
     };
+    let result = backtest::run(&candles, &mut BuyAndHold::default(), &config);
+    println!("Backtest (buy & hold):");
+    println!("   - Execution: {}", result.execution);
+    if result.execution.is_look_ahead() {
+        println!("   - WARNING: fills use prices not known when the signal was made");
+    }
+    if let Some(final_equity) = result.equity.values.last() {
+        println!("   - Final Equity: {:.2}", final_equity);
+        println!(
+            "   - Total Return: {:.4}",
+            final_equity / config.initial_cash - 1.0
+        );
+    }
+    println!("   - Cash Interest: {:.2}", result.cash_interest);
+    println!("   - Realized P&L: {:.2}", result.portfolio.realized_pnl());
+    println!(
+        "   - Unrealized P&L: {:.2}",
+        result.portfolio.unrealized_pnl()
+    );
+    if let Some(stats) = metrics::trade_stats(&result.trades) {
+        println!("   - Trades: {}", stats.trades);
+        println!("   - Win Rate: {:.2}%", stats.win_rate * 100.0);
+        println!("   - Profit Factor: {:.2}", stats.profit_factor);
+        println!("   - Expectancy: {:.2}", stats.expectancy);
+        println!("   - Payoff Ratio: {:.2}", stats.payoff_ratio);
+        println!(
+            "   - Longest Win/Loss Streak: {}/{}",
+            stats.longest_win_streak, stats.longest_loss_streak
+        );
+    } else {
+        println!("   - Trades: 0 closed");
+    }
+    let contributions = attribution::contributions(&result.equity, config.initial_cash);
+    for c in contributions
+        .symbols
+        .iter()
+        .chain(std::iter::once(&contributions.other))
+    {
+        println!(
+            "   - Contribution ({}): {:.2} P&L, {:.4} return",
+            c.symbol,
+            c.total_pnl(),
+            c.total_contribution()
+        );
+    }
+
+    // Metrics describe the strategy's account rather than the raw closes.
+    // Simple returns feed means, ratios, betas and the bootstrap; log returns
+    // feed anything that compounds (drawdowns, VaR horizons).
+    let returns = result.equity.simple_returns();
+    let simple = Returns::new(ReturnKind::Simple, returns.clone());
+    let log_returns = Returns::new(ReturnKind::Log, result.equity.returns());
+    let (dates, equity) = (&result.equity.dates, &result.equity.values);
+    if let Some(cagr) = metrics::cagr(equity) {
+        println!("   - CAGR: {:.4}", cagr);
+    }
+    if let Some(dd) = metrics::max_drawdown(dates, equity) {
+        println!(
+            "   - Max Drawdown: {:.2}% ({} -> {}, recovered: {})",
+            dd.depth * 100.0,
+            dd.peak_date,
+            dd.trough_date,
+            dd.recovery_date
+                .map_or("not yet".to_string(), |d| d.to_string())
+        );
+    }
+    if let Some(dd) = metrics::longest_drawdown(dates, equity) {
+        println!("   - Longest Drawdown: {} bars", dd.length);
+    }
+    if let Some(calmar) = metrics::calmar_ratio(equity) {
+        println!("   - Calmar Ratio: {:.4}", calmar);
+    }
+    if let Some(ulcer) = metrics::ulcer_index(equity) {
+        println!("   - Ulcer Index: {:.4}", ulcer);
+    }
+
+    // The benchmark file may be in any date order, so its returns are matched
+    // to the strategy's by date. Every benchmark comparison below uses the
+    // dates both have.
+    let return_dates = &result.equity.dates[1..];
+    let mut bench_by_date: Vec<_> = bench.iter().map(|c| (c.date, c.close)).collect();
+    bench_by_date.sort_by_key(|(date, _)| *date);
+    let bench_dates: Vec<_> = bench_by_date.iter().skip(1).map(|(d, _)| *d).collect();
+    let bench_closes_sorted: Vec<f64> = bench_by_date.iter().map(|(_, c)| *c).collect();
+    let bench_sorted_returns = metrics::simple_returns(&bench_closes_sorted);
+    let bench_on_date = scenario::dated(&bench_dates, &bench_sorted_returns);
+    let joined: Vec<(usize, f64)> = return_dates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, date)| bench_on_date.get(date).map(|b| (i, *b)))
+        .collect();
+    let dates_joined: Vec<_> = joined.iter().map(|(i, _)| return_dates[*i]).collect();
+    let returns_joined: Vec<f64> = joined.iter().map(|(i, _)| returns[*i]).collect();
+    let rf_joined: Vec<f64> = joined.iter().map(|(i, _)| rf_daily[*i]).collect();
+    let bench_joined: Vec<f64> = joined.iter().map(|(_, b)| *b).collect();
+    let simple_joined = Returns::new(ReturnKind::Simple, returns_joined.clone());
+    let bench_simple = Returns::new(ReturnKind::Simple, bench_joined.clone());
+
+    // Calendar tables
+    if let Some(table) = calendar::calendar_table(return_dates, &returns) {
+        print_calendar(&table);
+    }
+    if let Some(rows) =
+        calendar::annual_vs_benchmark(&dates_joined, &returns_joined, &dates_joined, &bench_joined)
+    {
+        println!("Annual Returns vs Benchmark:");
+        for row in rows {
+            println!(
+                "   - {}: {:>7.2}% vs {:>7.2}% ({:+.2}%)",
+                row.year,
+                row.strategy * 100.0,
+                row.benchmark * 100.0,
+                row.excess * 100.0
+            );
+        }
+    }
+    if let Some(days) = calendar::day_of_week(return_dates, &returns) {
+        println!("Day-of-week Seasonality:");
+        for (day, s) in days {
+            println!(
+                "   - {}: mean {:>8.4}%, sd {:.4}%, up {:.0}% ({} days)",
+                day,
+                s.mean * 100.0,
+                s.std_dev * 100.0,
+                s.hit_rate * 100.0,
+                s.count
+            );
+        }
+    }
+    if let Some(months) = calendar::month_of_year(return_dates, &returns) {
+        println!("Month-of-year Seasonality:");
+        for (month, s) in months {
+            println!(
+                "   - {:>2}: mean {:>7.2}%, up {:.0}% ({} years)",
+                month,
+                s.mean * 100.0,
+                s.hit_rate * 100.0,
+                s.count
+            );
+        }
+    }
+
+    // Stress the final portfolio
+    let asset_closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    let asset_dates: Vec<_> = candles.iter().skip(1).map(|c| c.date).collect();
+    let mut stress_data = ScenarioData {
+        benchmark: bench_on_date.clone(),
+        ..Default::default()
+    };
+    stress_data.symbols.insert(
+        config.symbol.clone(),
+        scenario::dated(&asset_dates, &metrics::simple_returns(&asset_closes)),
+    );
+    if let Some(rf_path) = &args.risk_free_file {
+        let levels = data::load_yield_series(rf_path, &args.rate_maturity)?;
+        stress_data.yield_changes = scenario::yield_changes(&levels);
+    }
+    let mut scenarios = vec![
+        Scenario::Market {
+            name: "Market -10%".to_string(),
+            shock: -0.10,
+        },
+        Scenario::Market {
+            name: "Market -20%".to_string(),
+            shock: -0.20,
+        },
+        Scenario::Rates {
+            name: "Rates +200bp".to_string(),
+            bp: 200.0,
+        },
+    ];
+    let bench_equity: Vec<f64> = bench_by_date.iter().map(|(_, c)| *c).collect();
+    let bench_all_dates: Vec<_> = bench_by_date.iter().map(|(d, _)| *d).collect();
+    if let Some(dd) = metrics::max_drawdown(&bench_all_dates, &bench_equity) {
+        scenarios.push(Scenario::Historical {
+            name: format!("Benchmark drawdown {} to {}", dd.peak_date, dd.trough_date),
+            start: dd.peak_date.succ_opt().unwrap_or(dd.peak_date),
+            end: dd.trough_date,
+        });
+    }
+    println!("Stress Scenarios (final portfolio):");
+    for r in scenario::run_scenarios(&result.portfolio, &stress_data, &scenarios) {
+        println!(
+            "   - {}: {:.2} ({:.2}%)",
+            r.name,
+            r.pnl,
+            r.return_pct * 100.0
+        );
+    }
+
+    if args.optimize {
+        let params: Vec<(usize, usize)> = (5..=50)
+            .step_by(5)
+            .flat_map(|fast| (20..=200).step_by(20).map(move |slow| (fast, slow)))
+            .filter(|(fast, slow)| fast < slow)
+            .collect();
+        let search = backtest::grid_search(&candles, &params, &config, |&(fast, slow)| {
+            SmaCrossover { fast, slow }
+        });
+        if let Some(report) = search {
+            let (fast, slow) = report.best;
+            println!("SMA Crossover Search ({} trials):", report.trials.len());
+            println!("   - Best Windows: {}/{}", fast, slow);
+            println!(
+                "   - Sharpe (annualized): {:.4}",
+                report.sharpe * 252f64.sqrt()
+            );
+            if let Some(psr) = report.psr {
+                println!("   - Probabilistic Sharpe (SR > 0): {:.4}", psr);
+            }
+            if let Some(dsr) = report.dsr {
+                println!("   - Deflated Sharpe: {:.4}", dsr);
+            }
+        }
+    }
 
     // --- Compute metrics ---
     if let Some((avr, std_dev)) = metrics::calc_stats(&returns) {
@@ -63,17 +381,215 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("   - Avg Daily Return: {:.6}", avr);
         println!("   - Daily Volatility: {:.6}", std_dev);
 
+        // Distribution diagnostics
+        if let (Some(skew), Some(kurt)) = (
+            metrics::skewness(&returns),
+            metrics::excess_kurtosis(&returns),
+        ) {
+            println!("   - Skewness: {:.4}", skew);
+            println!("   - Excess Kurtosis: {:.4}", kurt);
+        }
+        if let Some(jb) = metrics::jarque_bera(&returns) {
+            println!(
+                "   - Jarque-Bera: {:.2} (p = {:.4})",
+                jb.statistic, jb.p_value
+            );
+            if jb.p_value < 0.05 {
+                println!(
+                    "   - NOTE: returns are not normal; prefer the bootstrap intervals over Monte Carlo"
+                );
+            }
+        }
+        if let Some(lb) = metrics::ljung_box(&returns, 10) {
+            println!(
+                "   - Ljung-Box (10 lags): {:.2} (p = {:.4})",
+                lb.statistic, lb.p_value
+            );
+        }
+        if let Some(hurst) = metrics::hurst_exponent(&returns) {
+            println!("   - Hurst Exponent: {:.4}", hurst);
+        }
+
         // Monte Carlo Sharpe
         let n_sims = 1000;
-        let sharpe_sims = metrics::monte_carlo_sharpe(avr, std_dev, rf_daily[0] * 252.0, n_sims);
+        let seed = args.seed.unwrap_or_else(rand::random);
+        let sim = Simulation::new(n_sims, seed);
+        println!("   - Simulation Seed: {}", seed);
+        let rf_annual = rf_daily.iter().sum::<f64>() / rf_daily.len() as f64 * 252.0;
+        let sharpe_sims = metrics::monte_carlo_sharpe_seeded(avr, std_dev, rf_annual, &sim);
         let avg_sharpe = sharpe_sims.iter().sum::<f64>() / sharpe_sims.len() as f64;
         println!("   - Monte Carlo Avg Sharpe: {:.4}", avg_sharpe);
 
+        // Bootstrap over the realized returns
+        let method = args.bootstrap.resampling(args.block_size);
+        if let Some(boot) = metrics::bootstrap_seeded(&simple, &rf_daily, method, &sim) {
+            println!("   - Bootstrap 95% CI ({:?}):", method);
+            let dists = [
+                ("Sharpe", &boot.sharpe),
+                ("Max Drawdown", &boot.max_drawdown),
+                ("Terminal Wealth", &boot.terminal_wealth),
+            ];
+            for (name, samples) in dists {
+                if let Some((lo, hi)) = metrics::confidence_interval(samples, 0.95) {
+                    println!("       {:<15} [{:.4}, {:.4}]", name, lo, hi);
+                }
+            }
+        }
+
+        if let Some(sharpe) = metrics::sharpe_ratio(&simple, &rf_daily) {
+            println!("   - Sharpe Ratio: {:.4}", sharpe);
+        }
+        if let Some(sortino) = metrics::sortino_ratio(&simple, 0.0) {
+            println!("   - Sortino Ratio: {:.4}", sortino);
+        }
+        if let Some(omega) = metrics::omega_ratio(&simple, 0.0) {
+            println!("   - Omega Ratio: {:.4}", omega);
+        }
+        if let Some(tail) = metrics::tail_ratio(&simple) {
+            println!("   - Tail Ratio: {:.4}", tail);
+        }
+        if let Some(treynor) = metrics::treynor_ratio(&simple_joined, &bench_simple, &rf_joined) {
+            println!("   - Treynor Ratio: {:.4}", treynor);
+        }
+        if let Some(te) = metrics::tracking_error(&simple_joined, &bench_simple) {
+            println!("   - Tracking Error: {:.4}", te);
+        }
+        if let Some(ir) = metrics::information_ratio(&simple_joined, &bench_simple) {
+            println!("   - Information Ratio: {:.4}", ir);
+        }
+        if let (Some(up), Some(down)) = (
+            metrics::up_capture(&simple_joined, &bench_simple),
+            metrics::down_capture(&simple_joined, &bench_simple),
+        ) {
+            println!("   - Up / Down Capture: {:.4} / {:.4}", up, down);
+        }
+        if let Some(batting) = metrics::batting_average(&simple_joined, &bench_simple) {
+            println!("   - Batting Average: {:.2}%", batting * 100.0);
+        }
+        if let Some(active) = metrics::active_return(&simple_joined, &bench_simple) {
+            println!(
+                "   - Active Return: {:.4} (beta {:.4}, selection {:.4})",
+                active.active_return, active.beta_component, active.selection_component
+            );
+            println!("   - Active Risk: {:.4}", active.active_risk);
+        }
+        if let Some((up, down)) = metrics::up_down_correlation(&simple_joined, &bench_simple) {
+            println!("   - Up / Down Market Correlation: {:.4} / {:.4}", up, down);
+        }
+
+        // Rolling windows show when the edge appeared or decayed
+        let dates = &result.equity.dates[1..];
+        let window = args.rolling_window;
+        println!("   - Rolling {}-bar windows:", window);
+        let rolling = [
+            (
+                "Sharpe",
+                metrics::rolling_sharpe(dates, &simple, &rf_daily, window),
+            ),
+            (
+                "Volatility",
+                metrics::rolling_volatility(dates, &simple, window),
+            ),
+            (
+                "Beta",
+                metrics::rolling_beta(&dates_joined, &simple_joined, &bench_simple, window),
+            ),
+            (
+                "Correlation",
+                metrics::rolling_correlation(&dates_joined, &simple_joined, &bench_simple, window),
+            ),
+            (
+                "Max Drawdown",
+                metrics::rolling_max_drawdown(dates, &log_returns, window),
+            ),
+        ];
+        for (name, series) in rolling {
+            print_rolling(name, series.as_ref());
+        }
+
+        // Next-day volatility of the traded asset
+        println!("   - Next-day asset volatility (annualized):");
+        let range = |estimator| RangeVolatility { estimator, window };
+        let models: [(&str, &dyn VolatilityModel); 6] = [
+            ("Historical", &Historical { window }),
+            ("EWMA (0.94)", &Ewma::default()),
+            ("GARCH(1,1)", &GarchForecast),
+            ("Parkinson", &range(RangeEstimator::Parkinson)),
+            ("Garman-Klass", &range(RangeEstimator::GarmanKlass)),
+            ("Yang-Zhang", &range(RangeEstimator::YangZhang)),
+        ];
+        for (name, model) in models {
+            if let Some(vol) = model.forecast(&candles) {
+                println!("      {:<13} {:>7.2}%", name, vol * 252f64.sqrt() * 100.0);
+            }
+        }
+        if let Some(garch) = Garch::fit(&metrics::log_returns(&asset_closes)) {
+            println!(
+                "   - GARCH(1,1): alpha {:.4}, beta {:.4}, long-run vol {:.2}%",
+                garch.alpha,
+                garch.beta,
+                garch.long_run_variance().sqrt() * 252f64.sqrt() * 100.0
+            );
+        }
+
+        // Value-at-Risk
+        let confidence = 0.95;
+        println!("   - 1-day VaR / CVaR at {:.0}%:", confidence * 100.0);
+        let estimates = [
+            (
+                "Historical",
+                metrics::historical_var(&log_returns, confidence, 1),
+            ),
+            (
+                "Normal",
+                metrics::parametric_var(&log_returns, confidence, 1),
+            ),
+            (
+                "Cornish-Fisher",
+                metrics::cornish_fisher_var(&log_returns, confidence, 1),
+            ),
+            (
+                "Monte Carlo",
+                metrics::monte_carlo_var_seeded(
+                    &log_returns,
+                    confidence,
+                    1,
+                    &Simulation::new(10_000, seed),
+                ),
+            ),
+        ];
+        for (name, est) in estimates {
+            if let Some(est) = est {
+                println!("       {:<15} {:.4} / {:.4}", name, est.var, est.cvar);
+            }
+        }
+        // Out of sample: each day is checked against a forecast from the
+        // returns before it
+        let forecasts = metrics::rolling_var(&log_returns, confidence, args.var_window);
+        if let Some(forecasts) = forecasts
+            && let Some(exc) = metrics::var_exceptions(&log_returns, &forecasts)
+        {
+            if let Some(k) = metrics::kupiec_test(&exc, confidence) {
+                println!(
+                    "   - Kupiec POF: {} exceptions, p = {:.4}",
+                    k.exceptions, k.p_value
+                );
+            }
+            if let Some(c) = metrics::christoffersen_test(&exc) {
+                println!("   - Christoffersen Independence: p = {:.4}", c.p_value);
+            }
+        } else {
+            println!(
+                "   - VaR Backtest: needs more than {} returns",
+                args.var_window
+            );
+        }
+
         // Beta & Alpha
-        if let Some(b) = metrics::beta(&returns, &bench_returns) {
+        if let Some(b) = metrics::beta(&returns_joined, &bench_joined) {
             println!("   - Beta vs Benchmark: {:.4}", b);
 
-            if let Some(a) = metrics::alpha(&returns, &bench_returns, &rf_daily) {
+            if let Some(a) = metrics::alpha(&returns_joined, &bench_joined, &rf_joined) {
                 println!("   - Alpha vs Benchmark: {:.6}", a);
             } else {
                 eprintln!("Could not calculate alpha (check lengths)");
@@ -81,6 +597,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         } else {
             eprintln!("Could not calculate beta (check lengths)");
         }
+
+        let capm_errors = StdErrors::newey_west(returns_joined.len());
+        if let Some(fit) = regression::capm(&returns_joined, &bench_joined, &rf_joined, capm_errors)
+        {
+            print_regression("CAPM Regression", &fit);
+        }
+        if let Some(path) = &args.factors {
+            let factors = data::load_factors(path, args.factors_percent)?;
+            let dates = &result.equity.dates[1..];
+            let std_errors = StdErrors::newey_west(returns.len());
+            match regression::factor_model(dates, &returns, &factors, std_errors) {
+                Some(fit) => print_regression("Factor Regression", &fit),
+                None => eprintln!("Could not fit factor model (too few matching dates)"),
+            }
+        }
     } else {
         eprintln!("Not enough return data to calculate metrics");
     }
@@ -88,6 +619,75 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn print_calendar(table: &calendar::CalendarTable) {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    println!("Monthly Returns (%):");
+    print!("   {:<6}", "Year");
+    for m in MONTHS {
+        print!("{:>7}", m);
+    }
+    println!("{:>8}", "Year");
+    for (&year, &total) in &table.years {
+        print!("   {:<6}", year);
+        for month in 1..=12 {
+            match table.month(year, month) {
+                Some(r) => print!("{:>7.2}", r * 100.0),
+                None => print!("{:>7}", "-"),
+            }
+        }
+        println!("{:>8.2}", total * 100.0);
+    }
+}
+
+fn print_rolling(name: &str, series: Option<&metrics::TimeSeries>) {
+    let Some(series) = series.filter(|s| !s.is_empty()) else {
+        println!("       {:<13} n/a", name);
+        return;
+    };
+    let (lo, hi) = series
+        .values
+        .iter()
+        .enumerate()
+        .fold((0, 0), |(lo, hi), (i, v)| {
+            (
+                if *v < series.values[lo] { i } else { lo },
+                if *v > series.values[hi] { i } else { hi },
+            )
+        });
+    println!(
+        "       {:<13} latest {:>8.4}, min {:>8.4} ({}), max {:>8.4} ({})",
+        name,
+        series.values[series.len() - 1],
+        series.values[lo],
+        series.dates[lo],
+        series.values[hi],
+        series.dates[hi]
+    );
+}
+
+fn print_regression(title: &str, fit: &Regression) {
+    println!(
+        "{} ({} obs, {} SEs):",
+        title, fit.observations, fit.std_errors
+    );
+    let row = |name: &str, c: &regression::Coefficient| {
+        println!(
+            "   - {:<10} {:>10.6} (se {:.6}, t {:>7.3}, p {:.4})",
+            name, c.estimate, c.std_error, c.t_stat, c.p_value
+        );
+    };
+    row("Alpha", &fit.alpha);
+    for (name, beta) in fit.names.iter().zip(&fit.betas) {
+        row(name, beta);
+    }
+    println!(
+        "   - R²: {:.4} (adjusted {:.4})",
+        fit.r_squared, fit.adj_r_squared
+    );
+}
+
 
 
 
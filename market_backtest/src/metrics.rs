@@ -1,31 +1,170 @@
+use crate::backtest::Trade;
 use crate::data::Candle;
-use rand::thread_rng;
+use chrono::NaiveDate;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng, thread_rng};
 use rand_distr::{Distribution, Normal};
+use statrs::distribution::{ChiSquared, Continuous, ContinuousCDF, Normal as StatNormal};
 use statrs::statistics::Statistics;
 
-const TRADING_DAYS_PER_YEAR: usize = 252;
+pub const TRADING_DAYS_PER_YEAR: usize = 252;
 
 //
 // --------------------
 // Daily Returns
 // --------------------
-pub fn daily_returns(candles: &[Candle]) -> Vec<f64> {
-    let mut returns = Vec::new();
-    if candles.len() < 2 {
-        return returns;
+// Log returns of the closes
+pub fn daily_returns(candles: &[Candle]) -> Returns {
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    Returns::new(ReturnKind::Log, log_returns(&closes))
+}
+
+// Log returns between consecutive values of any price or equity series
+pub fn log_returns(values: &[f64]) -> Vec<f64> {
+    values.windows(2).map(|w| (w[1] / w[0]).ln()).collect()
+}
+
+// Simple (arithmetic) returns between consecutive values
+pub fn simple_returns(values: &[f64]) -> Vec<f64> {
+    values.windows(2).map(|w| w[1] / w[0] - 1.0).collect()
+}
+
+//
+// --------------------
+// Return Kinds
+// --------------------
+// Log returns add up over time, so they compound correctly when summed and
+// are what `equity_from_returns` and VaR horizons expect. Simple returns add
+// up across assets, so portfolios, means, ratios and the bootstrap use them.
+// Excess returns are simple returns minus the risk-free rate. Metrics that
+// only make sense for one kind take `Returns` and give `None` for any other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReturnKind {
+    Simple,
+    Log,
+    Excess,
+}
+
+// A return series tagged with its kind; convert before handing the values to
+// a metric that expects another kind
+#[derive(Debug, Clone, PartialEq)]
+pub struct Returns {
+    kind: ReturnKind,
+    values: Vec<f64>,
+}
+
+impl Returns {
+    pub fn new(kind: ReturnKind, values: Vec<f64>) -> Self {
+        Self { kind, values }
+    }
+
+    /// Simple or log returns of a price series (excess needs a risk-free rate)
+    pub fn from_prices(prices: &[f64], kind: ReturnKind) -> Option<Self> {
+        let values = match kind {
+            ReturnKind::Simple => simple_returns(prices),
+            ReturnKind::Log => log_returns(prices),
+            ReturnKind::Excess => return None,
+        };
+        Some(Self::new(kind, values))
+    }
+
+    pub fn kind(&self) -> ReturnKind {
+        self.kind
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The values, only if they are of `kind`
+    pub fn expect(&self, kind: ReturnKind) -> Option<&[f64]> {
+        (self.kind == kind).then_some(self.values.as_slice())
+    }
+
+    /// `None` for excess returns, whose risk-free part is unknown
+    pub fn to_simple(&self) -> Option<Returns> {
+        let values = match self.kind {
+            ReturnKind::Simple => self.values.clone(),
+            ReturnKind::Log => self.values.iter().map(|r| r.exp_m1()).collect(),
+            ReturnKind::Excess => return None,
+        };
+        Some(Self::new(ReturnKind::Simple, values))
+    }
+
+    /// `None` for excess returns, whose risk-free part is unknown
+    pub fn to_log(&self) -> Option<Returns> {
+        let values = match self.kind {
+            ReturnKind::Simple => self.values.iter().map(|r| r.ln_1p()).collect(),
+            ReturnKind::Log => self.values.clone(),
+            ReturnKind::Excess => return None,
+        };
+        Some(Self::new(ReturnKind::Log, values))
+    }
+
+    /// Subtracts the per-period simple risk-free rate
+    pub fn to_excess(&self, rf_rets: &[f64]) -> Option<Returns> {
+        let simple = self.to_simple()?;
+        let values = differences(&simple.values, rf_rets)?;
+        Some(Self::new(ReturnKind::Excess, values))
+    }
+}
+
+// Geometric annual growth rate of a simple or log return series
+pub fn cagr_from_returns(returns: &Returns) -> Option<f64> {
+    let log = returns.to_log()?;
+    if log.is_empty() {
+        return None;
     }
+    let years = log.len() as f64 / TRADING_DAYS_PER_YEAR as f64;
+    Some((log.values.iter().sum::<f64>() / years).exp_m1())
+}
 
-    for i in 1..candles.len() {
-        let ret = (candles[i].close / candles[i - 1].close).ln();
-        returns.push(ret);
+// Returns of a portfolio rebalanced to `weights` every period. Assets are
+// combined as simple returns (log returns do not add across assets), so the
+// result is simple, or excess if every input is excess.
+pub fn portfolio_returns(assets: &[Returns], weights: &[f64]) -> Option<Returns> {
+    let first = assets.first()?;
+    if assets.len() != weights.len() || assets.iter().any(|a| a.len() != first.len()) {
+        return None;
     }
-    returns
+    let kind = if assets.iter().all(|a| a.kind == ReturnKind::Excess) {
+        ReturnKind::Excess
+    } else {
+        ReturnKind::Simple
+    };
+    let simple: Vec<Returns> = match kind {
+        ReturnKind::Excess => assets.to_vec(),
+        _ => assets
+            .iter()
+            .map(Returns::to_simple)
+            .collect::<Option<_>>()?,
+    };
+
+    let values = (0..first.len())
+        .map(|t| {
+            simple
+                .iter()
+                .zip(weights)
+                .map(|(a, w)| w * a.values[t])
+                .sum()
+        })
+        .collect();
+    Some(Returns::new(kind, values))
 }
 
 //
 // --------------------
 // Basic Statistics
 // --------------------
+// Sample mean and standard deviation of any series, whatever its return kind
 pub fn calc_stats(returns: &[f64]) -> Option<(f64, f64)> {
     let count = returns.len() as f64;
     if count < 2.0 {
@@ -39,43 +178,258 @@ pub fn calc_stats(returns: &[f64]) -> Option<(f64, f64)> {
     Some((mean, std_dev))
 }
 
+//
+// --------------------
+// Simulation Runner
+// --------------------
+// Every simulation draws from its own RNG stream derived from (seed, index),
+// so a seed gives the same results whether the work runs on one thread or
+// many. Large runs are split across threads.
+const PARALLEL_MIN_SIMS: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Simulation {
+    pub n_sims: usize,
+    pub seed: u64,
+    /// Worker threads; 0 uses every available core
+    pub threads: usize,
+}
+
+impl Simulation {
+    pub fn new(n_sims: usize, seed: u64) -> Self {
+        Self {
+            n_sims,
+            seed,
+            threads: 0,
+        }
+    }
+
+    /// Seeds the run from an existing generator
+    pub fn from_rng<R: Rng + ?Sized>(n_sims: usize, rng: &mut R) -> Self {
+        Self::new(n_sims, rng.r#gen())
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    fn rng(&self, index: usize) -> StdRng {
+        StdRng::seed_from_u64(splitmix64(self.seed ^ splitmix64(index as u64)))
+    }
+
+    // Runs `simulate` once per simulation and returns the results in index order
+    fn run<T, F>(&self, simulate: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(&mut StdRng) -> T + Sync,
+    {
+        let threads = match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        if threads <= 1 || self.n_sims < PARALLEL_MIN_SIMS {
+            return (0..self.n_sims)
+                .map(|i| simulate(&mut self.rng(i)))
+                .collect();
+        }
+
+        let chunk = self.n_sims.div_ceil(threads);
+        let simulate = &simulate;
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.n_sims)
+                .step_by(chunk)
+                .map(|start| {
+                    scope.spawn(move || {
+                        (start..(start + chunk).min(self.n_sims))
+                            .map(|i| simulate(&mut self.rng(i)))
+                            .collect::<Vec<T>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().expect("simulation thread panicked"))
+                .collect()
+        })
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
 //
 // --------------------
 // Monte Carlo Sharpe Ratio
 // --------------------
-// `avr` and `std_dev` are daily mean & std deviation
+// `avr` and `std_dev` are daily mean & std deviation of simple returns
 // `rf` is annualized risk-free rate (scalar)
 // `n_sims` is number of Monte Carlo simulations
 pub fn monte_carlo_sharpe(avr: f64, std_dev: f64, rf: f64, n_sims: usize) -> Vec<f64> {
-    let mut sim_sharpe_r = Vec::with_capacity(n_sims);
+    let sim = Simulation::from_rng(n_sims, &mut thread_rng());
+    monte_carlo_sharpe_seeded(avr, std_dev, rf, &sim)
+}
+
+// Reproducible version: the same `sim` always gives the same ratios
+pub fn monte_carlo_sharpe_seeded(avr: f64, std_dev: f64, rf: f64, sim: &Simulation) -> Vec<f64> {
     if std_dev == 0.0 {
-        return sim_sharpe_r;
+        return Vec::new();
     }
-
-    let mut rng = thread_rng();
     let ret_dist = Normal::new(avr, std_dev).unwrap();
 
-    for _ in 0..n_sims {
+    sim.run(|rng| {
         // simulate one year of daily returns
         let sim_ret: Vec<f64> = (0..TRADING_DAYS_PER_YEAR)
-            .map(|_| ret_dist.sample(&mut rng))
+            .map(|_| ret_dist.sample(rng))
             .collect();
 
-        if let Some((sim_avr, sim_std)) = calc_stats(&sim_ret) {
-            let annual_ret = sim_avr * TRADING_DAYS_PER_YEAR as f64;
-            let annual_vol = sim_std * (TRADING_DAYS_PER_YEAR as f64).sqrt();
-            if annual_vol > 0.0 {
-                sim_sharpe_r.push((annual_ret - rf) / annual_vol);
+        let (sim_avr, sim_std) = calc_stats(&sim_ret)?;
+        let annual_ret = sim_avr * TRADING_DAYS_PER_YEAR as f64;
+        let annual_vol = sim_std * (TRADING_DAYS_PER_YEAR as f64).sqrt();
+        (annual_vol > 0.0).then(|| (annual_ret - rf) / annual_vol)
+    })
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+//
+// --------------------
+// Bootstrap Simulation
+// --------------------
+// Resamples the actual daily simple returns instead of assuming a normal
+// distribution. Block methods keep runs of consecutive days together so that
+// volatility clustering and autocorrelation survive the resampling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resampling {
+    /// Independent draws with replacement
+    Iid,
+    /// Politis-Romano: blocks of random (geometric) length with this mean
+    Stationary { mean_block: f64 },
+    /// Fixed-length blocks that wrap around the end of the series
+    Circular { block: usize },
+}
+
+// Distributions over all simulated paths, one entry per simulation
+#[derive(Debug, Clone, Default)]
+pub struct BootstrapResult {
+    pub sharpe: Vec<f64>,
+    /// Deepest drawdown of each path (a negative fraction)
+    pub max_drawdown: Vec<f64>,
+    /// Growth of 1.0 over each path
+    pub terminal_wealth: Vec<f64>,
+}
+
+// One resampled path with as many days as `returns`
+pub fn resample<R: Rng + ?Sized>(returns: &[f64], method: Resampling, rng: &mut R) -> Vec<f64> {
+    resample_indices(returns.len(), method, rng)
+        .into_iter()
+        .map(|i| returns[i])
+        .collect()
+}
+
+// Positions drawn from a series of `n` days, so that paired series (returns
+// and their risk-free rates) can be resampled together
+fn resample_indices<R: Rng + ?Sized>(n: usize, method: Resampling, rng: &mut R) -> Vec<usize> {
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut path = Vec::with_capacity(n);
+    match method {
+        Resampling::Iid => {
+            for _ in 0..n {
+                path.push(rng.gen_range(0..n));
+            }
+        }
+        Resampling::Stationary { mean_block } => {
+            let p_new_block = 1.0 / mean_block.max(1.0);
+            let mut i = rng.gen_range(0..n);
+            for _ in 0..n {
+                path.push(i);
+                i = if rng.gen_bool(p_new_block) {
+                    rng.gen_range(0..n)
+                } else {
+                    (i + 1) % n
+                };
             }
         }
+        Resampling::Circular { block } => {
+            let block = block.clamp(1, n);
+            while path.len() < n {
+                let start = rng.gen_range(0..n);
+                for k in 0..block.min(n - path.len()) {
+                    path.push((start + k) % n);
+                }
+            }
+        }
+    }
+    path
+}
+
+// `returns` must be simple returns and `rf_rets` the per-day risk-free series,
+// as in `sharpe_ratio`. Each day keeps its own risk-free rate when resampled,
+// so the Sharpe distribution is centred on the realized Sharpe ratio.
+pub fn bootstrap(
+    returns: &Returns,
+    rf_rets: &[f64],
+    method: Resampling,
+    n_sims: usize,
+) -> Option<BootstrapResult> {
+    let sim = Simulation::from_rng(n_sims, &mut thread_rng());
+    bootstrap_seeded(returns, rf_rets, method, &sim)
+}
+
+pub fn bootstrap_seeded(
+    returns: &Returns,
+    rf_rets: &[f64],
+    method: Resampling,
+    sim: &Simulation,
+) -> Option<BootstrapResult> {
+    let returns = returns.expect(ReturnKind::Simple)?;
+    if returns.len() < 2 || returns.len() != rf_rets.len() {
+        return None;
+    }
+
+    let paths = sim.run(|rng| {
+        let days = resample_indices(returns.len(), method, rng);
+        let path: Vec<f64> = days.iter().map(|&i| returns[i]).collect();
+        let rf_path: Vec<f64> = days.iter().map(|&i| rf_rets[i]).collect();
+        let sharpe = excess_sharpe(&path, &rf_path);
+        let equity = log_equity(&path.iter().map(|r| r.ln_1p()).collect::<Vec<_>>());
+        let max_dd = underwater(&equity).into_iter().fold(0.0, f64::min);
+        (sharpe, max_dd, *equity.last().unwrap())
+    });
+
+    let mut result = BootstrapResult::default();
+    for (sharpe, max_dd, wealth) in paths {
+        result.sharpe.extend(sharpe);
+        result.max_drawdown.push(max_dd);
+        result.terminal_wealth.push(wealth);
+    }
+    Some(result)
+}
+
+// Percentile interval holding the central `level` share of the samples
+pub fn confidence_interval(samples: &[f64], level: f64) -> Option<(f64, f64)> {
+    if level <= 0.0 || level >= 1.0 {
+        return None;
     }
-    sim_sharpe_r
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let tail = (1.0 - level) / 2.0;
+    Some((quantile(&sorted, tail)?, quantile(&sorted, 1.0 - tail)?))
 }
 
 //
 // --------------------
 // Beta
 // --------------------
+// Expects simple returns for both series
 pub fn beta(asset_rets: &[f64], market_rets: &[f64]) -> Option<f64> {
     if asset_rets.len() != market_rets.len() || asset_rets.len() < 2 {
         return None;
@@ -94,7 +448,8 @@ pub fn beta(asset_rets: &[f64], market_rets: &[f64]) -> Option<f64> {
 // --------------------
 // Alpha
 // --------------------
-// Uses daily risk-free returns series to compute excess returns
+// Uses daily risk-free returns series to compute excess returns. See
+// `regression::capm` for the same estimate with standard errors.
 pub fn alpha(asset_rets: &[f64], market_rets: &[f64], rf_rets: &[f64]) -> Option<f64> {
     if asset_rets.len() != market_rets.len() || asset_rets.len() != rf_rets.len() {
         return None;
@@ -112,369 +467,2280 @@ pub fn alpha(asset_rets: &[f64], market_rets: &[f64], rf_rets: &[f64]) -> Option
     Some(mean_asset - beta * mean_market)
 }
 
+//
+// --------------------
+// Risk-adjusted Ratios
+// --------------------
+// Inputs are daily simple returns. Means are annualized by multiplying by
+// TRADING_DAYS_PER_YEAR and volatilities by its square root, so every ratio
+// here is on the same annual scale.
+fn annualize_mean(daily: f64) -> f64 {
+    daily * TRADING_DAYS_PER_YEAR as f64
+}
 
+fn annualize_vol(daily: f64) -> f64 {
+    daily * (TRADING_DAYS_PER_YEAR as f64).sqrt()
+}
 
+fn differences(a: &[f64], b: &[f64]) -> Option<Vec<f64>> {
+    if a.len() != b.len() {
+        return None;
+    }
+    Some(a.iter().zip(b).map(|(x, y)| x - y).collect())
+}
 
-// use crate::data::Candle;
-// use rand::thread_rng;
-// use rand_distr::{Distribution, Normal};
-// use statrs::statistics::Statistics;
-// // Define the number of trading days in a year as a constant.
-// const TRADING_DAYS_PER_YEAR: usize = 252;
+// Realized Sharpe ratio of simple returns; `rf_rets` is the per-day
+// risk-free series
+pub fn sharpe_ratio(returns: &Returns, rf_rets: &[f64]) -> Option<f64> {
+    excess_sharpe(returns.expect(ReturnKind::Simple)?, rf_rets)
+}
 
-// pub fn daily_returns(candles: &[Candle]) -> Vec<f64> {
-//     let mut returns: Vec<f64> = Vec::new();
-//     //candles array has less then two candles we cannot calculate the daily return
-//     if candles.len() < 2 {
-//         return returns;
-//     }
-//     //loop from item 2 to the end of the list
-//     for candle in 1..candles.len() {
-//         let yesterday = &candles[candle - 1];
-//         let today = &candles[candle];
-//         //calculate log return
-//         let ret = (today.close / yesterday.close).ln();
-//         returns.push(ret);
-//     }
-//     returns
-// }
-// // //average returns
-// // pub fn mean(data: &[f64]) -> f64 {
-// //     let count = data.len() as f64;
-// //     if count == 0.0 {
-// //         return 0.0;
-// //     }
-// //     let sum: f64 = data.iter().sum();
-// //     sum / count
-// // }
-// // //measure volatility (how spread out the numbers in the data set are) risk
-// // pub fn variance(data: &[f64]) -> f64 {
-// //     let mut diffs: Vec<f64> = Vec::new();
-// //     for i in data {
-// //         let x = i - mean(data) as f64;
-// //         diffs.push(x);
-// //     }
-// //     let mut square_diffs: Vec<f64> = Vec::new();
-// //     for diff in diffs{
-// //         let square = diff * diff;
-// //          square_diffs.push(square);
-// //     }
+fn excess_sharpe(returns: &[f64], rf_rets: &[f64]) -> Option<f64> {
+    let excess = differences(returns, rf_rets)?;
+    let (mean, std_dev) = calc_stats(&excess)?;
+    if std_dev == 0.0 {
+        return None;
+    }
+    Some(annualize_mean(mean) / annualize_vol(std_dev))
+}
 
-// //     let sum: f64 = square_diffs.iter().sum();
-// //     let count:f64= square_diffs.len() as f64;
-// //     sum/(count - 1.0)
-// // }
+// Sortino ratio of simple returns against a daily minimum acceptable return
+// `mar`
+pub fn sortino_ratio(returns: &Returns, mar: f64) -> Option<f64> {
+    let returns = returns.expect(ReturnKind::Simple)?;
+    if returns.len() < 2 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().map(|r| r - mar).sum::<f64>() / n;
+    let downside = (returns
+        .iter()
+        .map(|r| (r - mar).min(0.0).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+    if downside == 0.0 {
+        return None;
+    }
+    Some(annualize_mean(mean) / annualize_vol(downside))
+}
 
-// pub fn calc_stats(returns: &[f64]) -> Option<(f64, f64)> {
-//     let count = returns.len() as f64;
-//     if count < 2.0 {
-//         return None;
-//     }
-//     let sum: f64 = returns.iter().sum();
-//     //average return
-//     let avr = sum / count;
-//     //variance -> for each data point, subtract the mean and square result
-//     //sum of squared diff
-//     let variance = returns
-//         .iter()
-//         .map(|value| {
-//             let diff = value - avr;
-//             diff * diff
-//         })
-//         .sum::<f64>()
-//         / (count - 1.0); // sum is a decimal and using n-1 to correct any errors in the small dataset
-//     //std dev is the squareroot of variance
-//     let std_dev = variance.sqrt();
-//     //if values vals exist, return  (Option)
-//     Some((avr, std_dev))
-// }
-// //rf = risk free return, n_sims = numb of simulations
-// pub fn monte_carlo_sharpe(avr: f64, std_dev: f64, rf: f64, n_sims: usize) -> Vec<f64> {
-//     // generate random returns, compute Sharpe ratios, return them
+// Probability-weighted gains over losses of simple returns relative to a
+// daily `threshold`
+pub fn omega_ratio(returns: &Returns, threshold: f64) -> Option<f64> {
+    let returns = returns.expect(ReturnKind::Simple)?;
+    let gains: f64 = returns.iter().map(|r| (r - threshold).max(0.0)).sum();
+    let losses: f64 = returns.iter().map(|r| (threshold - r).max(0.0)).sum();
+    if losses == 0.0 {
+        return None;
+    }
+    Some(gains / losses)
+}
 
-//     let mut sim_sharpe_r = Vec::with_capacity(n_sims);
-//     //handle 0 volatility by just returning empty vector since dividng by 0 is not possible
-//     if std_dev == 0.0 {
-//         return sim_sharpe_r;
-//     }
-//     // Standard normal distribution to generate random daily returns
-//     let mut rng = thread_rng();
-//     let ret_dist = Normal::new(avr, std_dev).unwrap();
+// Annualized excess return per unit of beta to the market; both series are
+// simple returns and `rf_rets` the per-day risk-free series
+pub fn treynor_ratio(returns: &Returns, market_rets: &Returns, rf_rets: &[f64]) -> Option<f64> {
+    let (returns, market_rets) = simple_pair(returns, market_rets)?;
+    let excess = differences(returns, rf_rets)?;
+    let excess_market = differences(market_rets, rf_rets)?;
+    let b = beta(&excess, &excess_market)?;
+    if b == 0.0 {
+        return None;
+    }
+    Some(annualize_mean(excess.mean()) / b)
+}
 
-//     for _ in 0..n_sims {
-//         //sim returns through this range (one year)
-//         let sim_ret: Vec<f64> = (0..TRADING_DAYS_PER_YEAR)
-//             .map(|_| ret_dist.sample(&mut rng))
-//             .collect();
+// Annualized volatility of simple returns in excess of the benchmark's
+pub fn tracking_error(returns: &Returns, bench_rets: &Returns) -> Option<f64> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let active = differences(returns, bench_rets)?;
+    let (_, std_dev) = calc_stats(&active)?;
+    Some(annualize_vol(std_dev))
+}
 
-//         //Compute mean & Std dev
-//         if let Some((avr, std_dev)) = calc_stats(&sim_ret) {
-//             //annualized return -> average return per year
-//             let annualized_return = avr * TRADING_DAYS_PER_YEAR as f64;
-//             let annualized_volatility = std_dev * (TRADING_DAYS_PER_YEAR as f64).sqrt();
-//             //sharpe ratio
-//             if annualized_volatility > 0.0 {
-//                 let sharpe = (annualized_return - rf) / annualized_volatility;
-//                 sim_sharpe_r.push(sharpe);
-//             }
-//         }
-//     }
-//     sim_sharpe_r
-// }
+// Annualized active return over tracking error, from simple returns
+pub fn information_ratio(returns: &Returns, bench_rets: &Returns) -> Option<f64> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let active = differences(returns, bench_rets)?;
+    let (mean, std_dev) = calc_stats(&active)?;
+    if std_dev == 0.0 {
+        return None;
+    }
+    Some(annualize_mean(mean) / annualize_vol(std_dev))
+}
 
-// //Beta(volatility and risk) of asset returns compared to market returns
-// pub fn beta(asset_rets: &[f64], market_rets: &[f64]) -> Option<f64> {
-//     //making sure both the csv
-//     if asset_rets.len() != market_rets.len() || asset_rets.len() < 2 {
-//         return None;
-//     }
-//     //covariance btween asset and market returns
-//     let cov = asset_rets.covariance(market_rets);
-//     //variance
-//     let var_market = market_rets.variance();
-//     if var_market == 0.0 {
-//         return None; // so we do not divide by zero
-//     }
-//     //Beta= Cov(asset,market)/var(market)
-//     Some(cov / var_market)
-// }
-// //Alpha (excess returns)
-// pub fn alpha(asset_rets: &[f64], market_rets: &[f64], rf: f64) -> Option<f64> {
-//     //beta
-//     let b = beta(asset_rets, market_rets)?;
+// 95th percentile simple return over the size of the 5th percentile one
+pub fn tail_ratio(returns: &Returns) -> Option<f64> {
+    let mut sorted = returns.expect(ReturnKind::Simple)?.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let right = quantile(&sorted, 0.95)?;
+    let left = quantile(&sorted, 0.05)?;
+    if left == 0.0 {
+        return None;
+    }
+    Some((right / left).abs())
+}
 
-//     //mean
-//     let avr_asset = asset_rets.mean();
-//     let avr_market = market_rets.mean();
+// Both series as simple returns, if they are
+fn simple_pair<'a>(a: &'a Returns, b: &'a Returns) -> Option<(&'a [f64], &'a [f64])> {
+    Some((a.expect(ReturnKind::Simple)?, b.expect(ReturnKind::Simple)?))
+}
 
-//     //Alpha = asset mean return - [Rf + Beta * (Market mean - Rf)]
-//     Some(avr_asset - (rf + b * (avr_market - rf)))
-// }
+// Linearly interpolated quantile of already sorted data
+fn quantile(sorted: &[f64], q: f64) -> Option<f64> {
+    if sorted.is_empty() || !(0.0..=1.0).contains(&q) {
+        return None;
+    }
+    let pos = q * (sorted.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    Some(sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64))
+}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::data::Candle;
+//
+// --------------------
+// Benchmark-relative Analytics
+// --------------------
+// Daily simple returns of the strategy and its benchmark, aligned by day;
+// any other kind gives `None`. Up (down) markets are the days the benchmark
+// rose (fell).
+fn market_days(returns: &[f64], bench_rets: &[f64], up: bool) -> Option<(Vec<f64>, Vec<f64>)> {
+    if returns.len() != bench_rets.len() {
+        return None;
+    }
+    let days: (Vec<f64>, Vec<f64>) = returns
+        .iter()
+        .zip(bench_rets)
+        .filter(|(_, b)| if up { **b > 0.0 } else { **b < 0.0 })
+        .map(|(r, b)| (*r, *b))
+        .unzip();
+    (!days.0.is_empty()).then_some(days)
+}
+
+fn geometric_mean(returns: &[f64]) -> f64 {
+    let log_sum: f64 = returns.iter().map(|r| r.ln_1p()).sum();
+    (log_sum / returns.len() as f64).exp_m1()
+}
+
+// Compounded strategy return over the benchmark's up days relative to the
+// benchmark's own; above 1.0 means it gained more than the benchmark
+pub fn up_capture(returns: &Returns, bench_rets: &Returns) -> Option<f64> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let (strategy, bench) = market_days(returns, bench_rets, true)?;
+    Some(geometric_mean(&strategy) / geometric_mean(&bench))
+}
+
+// As `up_capture` over down days; below 1.0 means it lost less
+pub fn down_capture(returns: &Returns, bench_rets: &Returns) -> Option<f64> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let (strategy, bench) = market_days(returns, bench_rets, false)?;
+    Some(geometric_mean(&strategy) / geometric_mean(&bench))
+}
+
+// Share of days the strategy beat the benchmark
+pub fn batting_average(returns: &Returns, bench_rets: &Returns) -> Option<f64> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let active = differences(returns, bench_rets)?;
+    if active.is_empty() {
+        return None;
+    }
+    Some(active.iter().filter(|a| **a > 0.0).count() as f64 / active.len() as f64)
+}
+
+// Correlation with the benchmark on (up, down) days
+pub fn up_down_correlation(returns: &Returns, bench_rets: &Returns) -> Option<(f64, f64)> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let (up_r, up_b) = market_days(returns, bench_rets, true)?;
+    let (down_r, down_b) = market_days(returns, bench_rets, false)?;
+    Some((correlation(&up_r, &up_b)?, correlation(&down_r, &down_b)?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveReturn {
+    /// Annualized mean of strategy minus benchmark returns
+    pub active_return: f64,
+    /// Part explained by beta differing from 1: (beta - 1) * benchmark mean
+    pub beta_component: f64,
+    /// The remainder, from security selection and timing
+    pub selection_component: f64,
+    /// Annualized standard deviation of the active returns (tracking error)
+    pub active_risk: f64,
+}
+
+// Splits the active return as r - b = alpha + (beta - 1) b
+pub fn active_return(returns: &Returns, bench_rets: &Returns) -> Option<ActiveReturn> {
+    let (returns, bench_rets) = simple_pair(returns, bench_rets)?;
+    let active = differences(returns, bench_rets)?;
+    let (mean, std_dev) = calc_stats(&active)?;
+    let beta = beta(returns, bench_rets)?;
+    let bench_mean = bench_rets.iter().sum::<f64>() / bench_rets.len() as f64;
+
+    let active_return = annualize_mean(mean);
+    let beta_component = annualize_mean((beta - 1.0) * bench_mean);
+    Some(ActiveReturn {
+        active_return,
+        beta_component,
+        selection_component: active_return - beta_component,
+        active_risk: annualize_vol(std_dev),
+    })
+}
+
+//
+// --------------------
+// Value-at-Risk & Expected Shortfall
+// --------------------
+// `returns` must be daily log returns, so multi-day horizons are sums. VaR
+// and CVaR are reported as positive losses (fractions of value) at
+// `confidence` (e.g. 0.99) over `horizon` trading days.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VarEstimate {
+    pub var: f64,
+    /// Expected shortfall: average loss beyond the VaR
+    pub cvar: f64,
+}
+
+fn valid_var_inputs(confidence: f64, horizon: usize) -> bool {
+    confidence > 0.0 && confidence < 1.0 && horizon > 0
+}
+
+// Overlapping `horizon`-day log returns
+fn horizon_returns(returns: &[f64], horizon: usize) -> Vec<f64> {
+    returns.windows(horizon).map(|w| w.iter().sum()).collect()
+}
+
+// VaR/CVaR read straight off a sample of outcomes
+fn empirical_var(mut outcomes: Vec<f64>, confidence: f64) -> Option<VarEstimate> {
+    outcomes.sort_by(f64::total_cmp);
+    let cutoff = quantile(&outcomes, 1.0 - confidence)?;
+    let tail: Vec<f64> = outcomes.into_iter().filter(|r| *r <= cutoff).collect();
+    Some(VarEstimate {
+        var: -cutoff,
+        cvar: -(tail.iter().sum::<f64>() / tail.len() as f64),
+    })
+}
+
+// Empirical quantile of overlapping `horizon`-day returns
+pub fn historical_var(returns: &Returns, confidence: f64, horizon: usize) -> Option<VarEstimate> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    if !valid_var_inputs(confidence, horizon) {
+        return None;
+    }
+    let outcomes = horizon_returns(returns, horizon);
+    if outcomes.is_empty() {
+        return None;
+    }
+    empirical_var(outcomes, confidence)
+}
+
+// Normal fit, with mean scaled by the horizon and volatility by its root
+pub fn parametric_var(returns: &Returns, confidence: f64, horizon: usize) -> Option<VarEstimate> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    if !valid_var_inputs(confidence, horizon) {
+        return None;
+    }
+    let (mean, std_dev) = calc_stats(returns)?;
+    let (mu, sigma) = scale_to_horizon(mean, std_dev, horizon);
+
+    let normal = StatNormal::new(0.0, 1.0).unwrap();
+    let alpha = 1.0 - confidence;
+    let z = normal.inverse_cdf(alpha);
+    Some(VarEstimate {
+        var: -(mu + z * sigma),
+        cvar: -(mu - sigma * normal.pdf(z) / alpha),
+    })
+}
+
+// Normal fit with the quantile adjusted for sample skew and excess kurtosis.
+// CVaR integrates the adjusted quantile over the tail numerically.
+pub fn cornish_fisher_var(
+    returns: &Returns,
+    confidence: f64,
+    horizon: usize,
+) -> Option<VarEstimate> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    if !valid_var_inputs(confidence, horizon) {
+        return None;
+    }
+    let (mean, std_dev) = calc_stats(returns)?;
+    let (mu, sigma) = scale_to_horizon(mean, std_dev, horizon);
+    let skew = skewness(returns)?;
+    let kurt = excess_kurtosis(returns)?;
+
+    let normal = StatNormal::new(0.0, 1.0).unwrap();
+    let alpha = 1.0 - confidence;
+    let loss_at = |p: f64| -(mu + cornish_fisher_z(normal.inverse_cdf(p), skew, kurt) * sigma);
+
+    const STEPS: usize = 1000;
+    let cvar = (0..STEPS)
+        .map(|i| loss_at(alpha * (i as f64 + 0.5) / STEPS as f64))
+        .sum::<f64>()
+        / STEPS as f64;
+    Some(VarEstimate {
+        var: loss_at(alpha),
+        cvar,
+    })
+}
+
+// Simulates `n_sims` horizon outcomes from a normal fit of `returns`
+pub fn monte_carlo_var(
+    returns: &Returns,
+    confidence: f64,
+    horizon: usize,
+    n_sims: usize,
+) -> Option<VarEstimate> {
+    let sim = Simulation::from_rng(n_sims, &mut thread_rng());
+    monte_carlo_var_seeded(returns, confidence, horizon, &sim)
+}
+
+pub fn monte_carlo_var_seeded(
+    returns: &Returns,
+    confidence: f64,
+    horizon: usize,
+    sim: &Simulation,
+) -> Option<VarEstimate> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    if !valid_var_inputs(confidence, horizon) || sim.n_sims == 0 {
+        return None;
+    }
+    let (mean, std_dev) = calc_stats(returns)?;
+    let ret_dist = Normal::new(mean, std_dev).ok()?;
+
+    let outcomes = sim.run(|rng| (0..horizon).map(|_| ret_dist.sample(rng)).sum());
+    empirical_var(outcomes, confidence)
+}
+
+fn scale_to_horizon(mean: f64, std_dev: f64, horizon: usize) -> (f64, f64) {
+    let h = horizon as f64;
+    (mean * h, std_dev * h.sqrt())
+}
+
+fn cornish_fisher_z(z: f64, skew: f64, kurt: f64) -> f64 {
+    z + (z.powi(2) - 1.0) * skew / 6.0 + (z.powi(3) - 3.0 * z) * kurt / 24.0
+        - (2.0 * z.powi(3) - 5.0 * z) * skew.powi(2) / 36.0
+}
+
+//
+// --------------------
+// Distribution Diagnostics
+// --------------------
+// `monte_carlo_sharpe`, `parametric_var` and `monte_carlo_var` assume normal,
+// independent returns. When these tests reject that, prefer the historical
+// and bootstrap estimates. The diagnostics describe the shape of any series,
+// so they take plain values of whichever return kind is being checked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestStatistic {
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+// Population skewness (third standardized moment)
+pub fn skewness(returns: &[f64]) -> Option<f64> {
+    let (m2, m3, _) = central_moments(returns)?;
+    Some(m3 / m2.powf(1.5))
+}
+
+// Population kurtosis minus 3, so a normal distribution scores 0
+pub fn excess_kurtosis(returns: &[f64]) -> Option<f64> {
+    let (m2, _, m4) = central_moments(returns)?;
+    Some(m4 / m2.powi(2) - 3.0)
+}
+
+fn central_moments(returns: &[f64]) -> Option<(f64, f64, f64)> {
+    if returns.len() < 3 {
+        return None;
+    }
+    let n = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / n;
+    let moment = |k: i32| returns.iter().map(|r| (r - mean).powi(k)).sum::<f64>() / n;
+    let m2 = moment(2);
+    if m2 == 0.0 {
+        return None;
+    }
+    Some((m2, moment(3), moment(4)))
+}
+
+// Normality test on skewness and excess kurtosis; chi-squared with 2 dof
+pub fn jarque_bera(returns: &[f64]) -> Option<TestStatistic> {
+    let skew = skewness(returns)?;
+    let kurt = excess_kurtosis(returns)?;
+    let statistic = returns.len() as f64 / 6.0 * (skew.powi(2) + kurt.powi(2) / 4.0);
+    Some(chi_squared_test(statistic, 2))
+}
+
+// Sample autocorrelation at `lag`
+pub fn autocorrelation(returns: &[f64], lag: usize) -> Option<f64> {
+    let n = returns.len();
+    if lag == 0 || lag >= n {
+        return None;
+    }
+    let mean = returns.iter().sum::<f64>() / n as f64;
+    let denom: f64 = returns.iter().map(|r| (r - mean).powi(2)).sum();
+    if denom == 0.0 {
+        return None;
+    }
+    let num: f64 = (lag..n)
+        .map(|t| (returns[t] - mean) * (returns[t - lag] - mean))
+        .sum();
+    Some(num / denom)
+}
+
+// Joint test that the first `lags` autocorrelations are zero
+pub fn ljung_box(returns: &[f64], lags: usize) -> Option<TestStatistic> {
+    let n = returns.len() as f64;
+    if lags == 0 {
+        return None;
+    }
+    let mut q = 0.0;
+    for k in 1..=lags {
+        q += autocorrelation(returns, k)?.powi(2) / (n - k as f64);
+    }
+    Some(chi_squared_test(n * (n + 2.0) * q, lags))
+}
+
+fn chi_squared_test(statistic: f64, dof: usize) -> TestStatistic {
+    let chi2 = ChiSquared::new(dof as f64).unwrap();
+    TestStatistic {
+        statistic,
+        p_value: 1.0 - chi2.cdf(statistic),
+    }
+}
+
+// Rescaled-range estimate: ~0.5 for independent returns, above for trending
+// (persistent) and below for mean-reverting series. Needs 32+ returns.
+pub fn hurst_exponent(returns: &[f64]) -> Option<f64> {
+    let mut points = Vec::new();
+    let mut size = 8;
+    while size <= returns.len() / 2 {
+        let ratios: Vec<f64> = returns
+            .chunks_exact(size)
+            .filter_map(rescaled_range)
+            .collect();
+        if !ratios.is_empty() {
+            let mean = ratios.iter().sum::<f64>() / ratios.len() as f64;
+            points.push(((size as f64).ln(), mean.ln()));
+        }
+        size *= 2;
+    }
+    if points.len() < 2 {
+        return None;
+    }
+
+    // slope of log(R/S) on log(size)
+    let (xs, ys): (Vec<f64>, Vec<f64>) = points.into_iter().unzip();
+    Some(xs.as_slice().covariance(ys.as_slice()) / xs.as_slice().variance())
+}
+
+fn rescaled_range(chunk: &[f64]) -> Option<f64> {
+    let n = chunk.len() as f64;
+    let mean = chunk.iter().sum::<f64>() / n;
+    let (mut cum, mut lo, mut hi) = (0.0, 0.0_f64, 0.0_f64);
+    for r in chunk {
+        cum += r - mean;
+        lo = lo.min(cum);
+        hi = hi.max(cum);
+    }
+    let sd = (chunk.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n).sqrt();
+    (sd > 0.0).then(|| (hi - lo) / sd)
+}
+
+//
+// --------------------
+// VaR Backtesting
+// --------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VarTest {
+    pub observations: usize,
+    pub exceptions: usize,
+    /// Likelihood-ratio statistic
+    pub statistic: f64,
+    pub p_value: f64,
+}
+
+// One-day historical VaR forecasts for `returns[window..]`, each fitted only
+// on the `window` returns before the day it covers, so exceptions against
+// them are out of sample. `None` if there are no more than `window` returns.
+pub fn rolling_var(returns: &Returns, confidence: f64, window: usize) -> Option<Vec<f64>> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    if window == 0 || returns.len() <= window || !valid_var_inputs(confidence, 1) {
+        return None;
+    }
+    (window..returns.len())
+        .map(|t| empirical_var(returns[t - window..t].to_vec(), confidence).map(|est| est.var))
+        .collect()
+}
+
+// Marks each day whose log-return loss exceeded that day's VaR forecast.
+// `var` covers the last `var.len()` days of `returns`, as `rolling_var` gives.
+pub fn var_exceptions(returns: &Returns, var: &[f64]) -> Option<Vec<bool>> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    let start = returns.len().checked_sub(var.len())?;
+    let days = &returns[start..];
+    Some(days.iter().zip(var).map(|(r, v)| -r > *v).collect())
+}
+
+// Kupiec proportion-of-failures test: is the exception rate consistent with
+// the VaR confidence level?
+pub fn kupiec_test(exceptions: &[bool], confidence: f64) -> Option<VarTest> {
+    if exceptions.is_empty() || confidence <= 0.0 || confidence >= 1.0 {
+        return None;
+    }
+    let n = exceptions.len() as f64;
+    let x = exceptions.iter().filter(|e| **e).count() as f64;
+    let p = 1.0 - confidence;
+    let observed = x / n;
+
+    let log_null = xlny(n - x, 1.0 - p) + xlny(x, p);
+    let log_alt = xlny(n - x, 1.0 - observed) + xlny(x, observed);
+    Some(lr_test(exceptions, -2.0 * (log_null - log_alt), 1.0))
+}
+
+// Christoffersen independence test: do exceptions cluster?
+pub fn christoffersen_test(exceptions: &[bool]) -> Option<VarTest> {
+    if exceptions.len() < 2 {
+        return None;
+    }
+    let (mut n00, mut n01, mut n10, mut n11) = (0.0, 0.0, 0.0, 0.0);
+    for w in exceptions.windows(2) {
+        match (w[0], w[1]) {
+            (false, false) => n00 += 1.0,
+            (false, true) => n01 += 1.0,
+            (true, false) => n10 += 1.0,
+            (true, true) => n11 += 1.0,
+        }
+    }
+
+    let rate = |hits: f64, total: f64| if total > 0.0 { hits / total } else { 0.0 };
+    let pi0 = rate(n01, n00 + n01);
+    let pi1 = rate(n11, n10 + n11);
+    let pi = rate(n01 + n11, n00 + n01 + n10 + n11);
+
+    let log_null = xlny(n00 + n10, 1.0 - pi) + xlny(n01 + n11, pi);
+    let log_alt = xlny(n00, 1.0 - pi0) + xlny(n01, pi0) + xlny(n10, 1.0 - pi1) + xlny(n11, pi1);
+    Some(lr_test(exceptions, -2.0 * (log_null - log_alt), 1.0))
+}
+
+fn lr_test(exceptions: &[bool], statistic: f64, dof: f64) -> VarTest {
+    let statistic = statistic.max(0.0);
+    let chi2 = ChiSquared::new(dof).unwrap();
+    VarTest {
+        observations: exceptions.len(),
+        exceptions: exceptions.iter().filter(|e| **e).count(),
+        statistic,
+        p_value: 1.0 - chi2.cdf(statistic),
+    }
+}
+
+// x * ln(y), taking 0 * ln(0) as 0
+fn xlny(x: f64, y: f64) -> f64 {
+    if x == 0.0 { 0.0 } else { x * y.ln() }
+}
+
+//
+// --------------------
+// Probabilistic & Deflated Sharpe Ratio
+// --------------------
+// Bailey & Lopez de Prado. `returns` are simple or excess returns (any other
+// kind gives `None`) and Sharpe ratios here are per period (not annualized),
+// matching their frequency.
+const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+
+fn arithmetic(returns: &Returns) -> Option<&[f64]> {
+    match returns.kind() {
+        ReturnKind::Simple | ReturnKind::Excess => Some(returns.values()),
+        ReturnKind::Log => None,
+    }
+}
+
+// Mean over standard deviation of `returns`, without annualizing
+pub fn per_period_sharpe(returns: &Returns) -> Option<f64> {
+    let (mean, std_dev) = calc_stats(arithmetic(returns)?)?;
+    (std_dev > 0.0).then(|| mean / std_dev)
+}
+
+// Probability that the true Sharpe exceeds `benchmark_sharpe`, given the
+// sample length, skew and kurtosis of `returns`
+pub fn probabilistic_sharpe_ratio(returns: &Returns, benchmark_sharpe: f64) -> Option<f64> {
+    let sharpe = per_period_sharpe(returns)?;
+    let returns = arithmetic(returns)?;
+    let skew = skewness(returns)?;
+    let kurt = excess_kurtosis(returns)? + 3.0;
+
+    let denom = 1.0 - skew * sharpe + (kurt - 1.0) / 4.0 * sharpe.powi(2);
+    if denom <= 0.0 {
+        return None;
+    }
+    let z = (sharpe - benchmark_sharpe) * ((returns.len() - 1) as f64).sqrt() / denom.sqrt();
+    Some(StatNormal::new(0.0, 1.0).unwrap().cdf(z))
+}
+
+// Expected best Sharpe among `n_trials` unskilled strategies whose Sharpe
+// ratios have variance `trial_variance`
+pub fn expected_max_sharpe(n_trials: usize, trial_variance: f64) -> Option<f64> {
+    if n_trials < 2 || trial_variance < 0.0 {
+        return None;
+    }
+    let normal = StatNormal::new(0.0, 1.0).unwrap();
+    let n = n_trials as f64;
+    let z = (1.0 - EULER_MASCHERONI) * normal.inverse_cdf(1.0 - 1.0 / n)
+        + EULER_MASCHERONI * normal.inverse_cdf(1.0 - 1.0 / (n * std::f64::consts::E));
+    Some(trial_variance.sqrt() * z)
+}
+
+// PSR of the selected strategy against the Sharpe expected from luck alone
+// after trying every configuration in `trial_sharpes` (per-period ratios).
+// `None` entries are trials without a Sharpe (e.g. flat equity): they count
+// towards the number of trials but not the variance.
+pub fn deflated_sharpe_ratio(returns: &Returns, trial_sharpes: &[Option<f64>]) -> Option<f64> {
+    let known: Vec<f64> = trial_sharpes.iter().flatten().copied().collect();
+    if known.len() < 2 {
+        return None;
+    }
+    let threshold = expected_max_sharpe(trial_sharpes.len(), known.variance())?;
+    probabilistic_sharpe_ratio(returns, threshold)
+}
+
+//
+// --------------------
+// Drawdowns
+// --------------------
+// All of these take an equity (or price) series; `equity_from_returns`
+// builds one from log `Returns`, such as those `daily_returns` gives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drawdown {
+    pub peak_date: NaiveDate,
+    pub trough_date: NaiveDate,
+    /// `None` if the series ends before regaining the peak
+    pub recovery_date: Option<NaiveDate>,
+    /// Peak-to-trough loss as a (negative) fraction
+    pub depth: f64,
+    /// Bars from the peak until recovery (or the end of the series)
+    pub length: usize,
+}
+
+// Growth of 1.0 invested, one value per log return plus the starting value
+pub fn equity_from_returns(returns: &Returns) -> Option<Vec<f64>> {
+    Some(log_equity(returns.expect(ReturnKind::Log)?))
+}
+
+fn log_equity(log_returns: &[f64]) -> Vec<f64> {
+    let mut equity = Vec::with_capacity(log_returns.len() + 1);
+    equity.push(1.0);
+    let mut cum = 0.0;
+    for r in log_returns {
+        cum += r;
+        equity.push(cum.exp());
+    }
+    equity
+}
+
+// Fractional distance below the running peak (0.0 at new highs)
+pub fn underwater(equity: &[f64]) -> Vec<f64> {
+    let mut peak = f64::MIN;
+    equity
+        .iter()
+        .map(|&v| {
+            peak = peak.max(v);
+            v / peak - 1.0
+        })
+        .collect()
+}
+
+// Every drawdown episode in date order; `dates` must line up with `equity`
+pub fn drawdowns(dates: &[NaiveDate], equity: &[f64]) -> Vec<Drawdown> {
+    let mut episodes = Vec::new();
+    if dates.len() != equity.len() || equity.is_empty() {
+        return episodes;
+    }
+
+    let episode = |peak: usize, trough: usize, end: usize, recovered: bool| Drawdown {
+        peak_date: dates[peak],
+        trough_date: dates[trough],
+        recovery_date: recovered.then_some(dates[end]),
+        depth: equity[trough] / equity[peak] - 1.0,
+        length: end - peak,
+    };
+
+    let mut peak = 0;
+    let mut trough: Option<usize> = None;
+    for i in 1..equity.len() {
+        if equity[i] >= equity[peak] {
+            if let Some(t) = trough.take() {
+                episodes.push(episode(peak, t, i, true));
+            }
+            peak = i;
+        } else if trough.is_none_or(|t| equity[i] < equity[t]) {
+            trough = Some(i);
+        }
+    }
+    if let Some(t) = trough {
+        episodes.push(episode(peak, t, equity.len() - 1, false));
+    }
+    episodes
+}
+
+pub fn max_drawdown(dates: &[NaiveDate], equity: &[f64]) -> Option<Drawdown> {
+    drawdowns(dates, equity)
+        .into_iter()
+        .min_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+// Drawdown that stayed below its peak the most bars
+pub fn longest_drawdown(dates: &[NaiveDate], equity: &[f64]) -> Option<Drawdown> {
+    drawdowns(dates, equity)
+        .into_iter()
+        .max_by_key(|d| d.length)
+}
+
+// The `n` deepest drawdowns, deepest first
+pub fn top_drawdowns(dates: &[NaiveDate], equity: &[f64], n: usize) -> Vec<Drawdown> {
+    let mut episodes = drawdowns(dates, equity);
+    episodes.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+    episodes.truncate(n);
+    episodes
+}
+
+// Annualized growth rate over / size of the max drawdown
+pub fn calmar_ratio(equity: &[f64]) -> Option<f64> {
+    let growth = cagr(equity)?;
+    let max_dd = underwater(equity).into_iter().fold(0.0, f64::min);
+    if max_dd == 0.0 {
+        return None;
+    }
+    Some(growth / -max_dd)
+}
+
+// Root-mean-square drawdown, in percent as Martin defined it
+pub fn ulcer_index(equity: &[f64]) -> Option<f64> {
+    if equity.is_empty() {
+        return None;
+    }
+    let sum_sq: f64 = underwater(equity).iter().map(|d| (d * 100.0).powi(2)).sum();
+    Some((sum_sq / equity.len() as f64).sqrt())
+}
+
+// Compound annual growth of an equity series, one value per trading day
+pub fn cagr(equity: &[f64]) -> Option<f64> {
+    let (first, last) = (*equity.first()?, *equity.last()?);
+    if equity.len() < 2 || first <= 0.0 {
+        return None;
+    }
+    let years = (equity.len() - 1) as f64 / TRADING_DAYS_PER_YEAR as f64;
+    Some((last / first).powf(1.0 / years) - 1.0)
+}
+
+//
+// --------------------
+// Rolling Windows
+// --------------------
+// Each value covers the `window` returns ending on (and dated at) its date,
+// with the same return kind as the full-period metric.
+// `dates[i]` is the date of `returns[i]`; windows where the metric is
+// undefined (e.g. zero variance) are left out rather than filled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeSeries {
+    pub dates: Vec<NaiveDate>,
+    pub values: Vec<f64>,
+}
+
+impl TimeSeries {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Value on `date`, if one was computed for it
+    pub fn get(&self, date: NaiveDate) -> Option<f64> {
+        let i = self.dates.binary_search(&date).ok()?;
+        Some(self.values[i])
+    }
+}
+
+fn rolling<F>(dates: &[NaiveDate], len: usize, window: usize, metric: F) -> Option<TimeSeries>
+where
+    F: Fn(std::ops::Range<usize>) -> Option<f64>,
+{
+    if window < 2 || dates.len() != len {
+        return None;
+    }
+    let mut series = TimeSeries::default();
+    for end in window..=len {
+        if let Some(v) = metric(end - window..end) {
+            series.dates.push(dates[end - 1]);
+            series.values.push(v);
+        }
+    }
+    Some(series)
+}
+
+// Annualized standard deviation of simple returns
+pub fn rolling_volatility(
+    dates: &[NaiveDate],
+    returns: &Returns,
+    window: usize,
+) -> Option<TimeSeries> {
+    let returns = returns.expect(ReturnKind::Simple)?;
+    rolling(dates, returns.len(), window, |r| {
+        calc_stats(&returns[r]).map(|(_, sd)| annualize_vol(sd))
+    })
+}
+
+// Annualized Sharpe, as `sharpe_ratio`
+pub fn rolling_sharpe(
+    dates: &[NaiveDate],
+    returns: &Returns,
+    rf_rets: &[f64],
+    window: usize,
+) -> Option<TimeSeries> {
+    let returns = returns.expect(ReturnKind::Simple)?;
+    if rf_rets.len() != returns.len() {
+        return None;
+    }
+    rolling(dates, returns.len(), window, |r| {
+        excess_sharpe(&returns[r.clone()], &rf_rets[r])
+    })
+}
+
+// Beta of simple returns, as `beta`
+pub fn rolling_beta(
+    dates: &[NaiveDate],
+    asset_rets: &Returns,
+    market_rets: &Returns,
+    window: usize,
+) -> Option<TimeSeries> {
+    let (asset_rets, market_rets) = simple_pair(asset_rets, market_rets)?;
+    if market_rets.len() != asset_rets.len() {
+        return None;
+    }
+    rolling(dates, asset_rets.len(), window, |r| {
+        beta(&asset_rets[r.clone()], &market_rets[r])
+    })
+}
+
+// Daily Jensen's alpha of simple returns, as `alpha`
+pub fn rolling_alpha(
+    dates: &[NaiveDate],
+    asset_rets: &Returns,
+    market_rets: &Returns,
+    rf_rets: &[f64],
+    window: usize,
+) -> Option<TimeSeries> {
+    let (asset_rets, market_rets) = simple_pair(asset_rets, market_rets)?;
+    if market_rets.len() != asset_rets.len() || rf_rets.len() != asset_rets.len() {
+        return None;
+    }
+    rolling(dates, asset_rets.len(), window, |r| {
+        alpha(&asset_rets[r.clone()], &market_rets[r.clone()], &rf_rets[r])
+    })
+}
+
+// Pearson correlation of two simple return series
+pub fn rolling_correlation(
+    dates: &[NaiveDate],
+    a: &Returns,
+    b: &Returns,
+    window: usize,
+) -> Option<TimeSeries> {
+    let (a, b) = simple_pair(a, b)?;
+    if a.len() != b.len() {
+        return None;
+    }
+    rolling(dates, a.len(), window, |r| {
+        correlation(&a[r.clone()], &b[r])
+    })
+}
+
+// Deepest drawdown (negative fraction) of the equity grown from the window's
+// log returns alone
+pub fn rolling_max_drawdown(
+    dates: &[NaiveDate],
+    returns: &Returns,
+    window: usize,
+) -> Option<TimeSeries> {
+    let returns = returns.expect(ReturnKind::Log)?;
+    rolling(dates, returns.len(), window, |r| {
+        underwater(&log_equity(&returns[r]))
+            .into_iter()
+            .reduce(f64::min)
+    })
+}
+
+fn correlation(a: &[f64], b: &[f64]) -> Option<f64> {
+    let denom = a.std_dev() * b.std_dev();
+    if a.len() < 2 || denom == 0.0 || denom.is_nan() {
+        return None;
+    }
+    Some(a.covariance(b) / denom)
+}
+
+//
+// --------------------
+// Trade Statistics
+// --------------------
+// Computed from closed round trips; breakeven trades count toward the total
+// but are neither wins nor losses and end any streak.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeStats {
+    pub trades: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub win_rate: f64,
+    pub avg_win: f64,
+    /// Average losing trade (a negative number)
+    pub avg_loss: f64,
+    /// Average win over the size of the average loss
+    pub payoff_ratio: f64,
+    /// Gross profit over gross loss
+    pub profit_factor: f64,
+    /// Average P&L per trade
+    pub expectancy: f64,
+    pub longest_win_streak: usize,
+    pub longest_loss_streak: usize,
+    pub avg_bars_held: f64,
+}
+
+pub fn trade_stats(trades: &[Trade]) -> Option<TradeStats> {
+    if trades.is_empty() {
+        return None;
+    }
+
+    let count = trades.len() as f64;
+    let gross_win: f64 = trades.iter().map(|t| t.pnl.max(0.0)).sum();
+    let gross_loss: f64 = trades.iter().map(|t| t.pnl.min(0.0)).sum();
+    let wins = trades.iter().filter(|t| t.pnl > 0.0).count();
+    let losses = trades.iter().filter(|t| t.pnl < 0.0).count();
+
+    let avg_win = if wins > 0 {
+        gross_win / wins as f64
+    } else {
+        0.0
+    };
+    let avg_loss = if losses > 0 {
+        gross_loss / losses as f64
+    } else {
+        0.0
+    };
+
+    let (mut win_streak, mut loss_streak) = (0, 0);
+    let (mut longest_win_streak, mut longest_loss_streak) = (0, 0);
+    for t in trades {
+        win_streak = if t.pnl > 0.0 { win_streak + 1 } else { 0 };
+        loss_streak = if t.pnl < 0.0 { loss_streak + 1 } else { 0 };
+        longest_win_streak = longest_win_streak.max(win_streak);
+        longest_loss_streak = longest_loss_streak.max(loss_streak);
+    }
+
+    Some(TradeStats {
+        trades: trades.len(),
+        wins,
+        losses,
+        win_rate: wins as f64 / count,
+        avg_win,
+        avg_loss,
+        payoff_ratio: ratio_or_inf(avg_win, -avg_loss),
+        profit_factor: ratio_or_inf(gross_win, -gross_loss),
+        expectancy: (gross_win + gross_loss) / count,
+        longest_win_streak,
+        longest_loss_streak,
+        avg_bars_held: trades.iter().map(|t| t.bars_held as f64).sum::<f64>() / count,
+    })
+}
+
+// Profit-over-loss style ratio: infinite when there is profit but no loss
+fn ratio_or_inf(gain: f64, loss: f64) -> f64 {
+    if loss > 0.0 {
+        gain / loss
+    } else if gain > 0.0 {
+        f64::INFINITY
+    } else {
+        0.0
+    }
+}
+
+// Kelly fraction W - (1 - W) / R from per-trade P&Ls, with W the win rate
+// and R the payoff ratio. Negative when the trades have no edge (-inf with no
+// winners); equals W with no losers.
+pub fn kelly_fraction(pnls: &[f64]) -> Option<f64> {
+    if pnls.is_empty() {
+        return None;
+    }
+    let wins: Vec<f64> = pnls.iter().copied().filter(|p| *p > 0.0).collect();
+    let losses: Vec<f64> = pnls.iter().copied().filter(|p| *p < 0.0).collect();
+    let avg = |v: &[f64]| {
+        if v.is_empty() {
+            0.0
+        } else {
+            v.iter().sum::<f64>() / v.len() as f64
+        }
+    };
+    let win_rate = wins.len() as f64 / pnls.len() as f64;
+    let payoff = ratio_or_inf(avg(&wins), -avg(&losses));
+    Some(win_rate - (1.0 - win_rate) / payoff)
+}
+
+
+
+
+// use crate::data::Candle;
+// use rand::thread_rng;
+// use rand_distr::{Distribution, Normal};
+// use statrs::statistics::Statistics;
+// // Define the number of trading days in a year as a constant.
+// const TRADING_DAYS_PER_YEAR: usize = 252;
+
+// pub fn daily_returns(candles: &[Candle]) -> Vec<f64> {
+//     let mut returns: Vec<f64> = Vec::new();
+//     //candles array has less then two candles we cannot calculate the daily return
+//     if candles.len() < 2 {
+//         return returns;
+//     }
+//     //loop from item 2 to the end of the list
+//     for candle in 1..candles.len() {
+//         let yesterday = &candles[candle - 1];
+//         let today = &candles[candle];
+//         //calculate log return
+//         let ret = (today.close / yesterday.close).ln();
+//         returns.push(ret);
+//     }
+//     returns
+// }
+// // //average returns
+// // pub fn mean(data: &[f64]) -> f64 {
+// //     let count = data.len() as f64;
+// //     if count == 0.0 {
+// //         return 0.0;
+// //     }
+// //     let sum: f64 = data.iter().sum();
+// //     sum / count
+// // }
+// // //measure volatility (how spread out the numbers in the data set are) risk
+// // pub fn variance(data: &[f64]) -> f64 {
+// //     let mut diffs: Vec<f64> = Vec::new();
+// //     for i in data {
+// //         let x = i - mean(data) as f64;
+// //         diffs.push(x);
+// //     }
+// //     let mut square_diffs: Vec<f64> = Vec::new();
+// //     for diff in diffs{
+// //         let square = diff * diff;
+// //          square_diffs.push(square);
+// //     }
+
+// //     let sum: f64 = square_diffs.iter().sum();
+// //     let count:f64= square_diffs.len() as f64;
+// //     sum/(count - 1.0)
+// // }
+
+// pub fn calc_stats(returns: &[f64]) -> Option<(f64, f64)> {
+//     let count = returns.len() as f64;
+//     if count < 2.0 {
+//         return None;
+//     }
+//     let sum: f64 = returns.iter().sum();
+//     //average return
+//     let avr = sum / count;
+//     //variance -> for each data point, subtract the mean and square result
+//     //sum of squared diff
+//     let variance = returns
+//         .iter()
+//         .map(|value| {
+//             let diff = value - avr;
+//             diff * diff
+//         })
+//         .sum::<f64>()
+//         / (count - 1.0); // sum is a decimal and using n-1 to correct any errors in the small dataset
+//     //std dev is the squareroot of variance
+//     let std_dev = variance.sqrt();
+//     //if values vals exist, return  (Option)
+//     Some((avr, std_dev))
+// }
+// //rf = risk free return, n_sims = numb of simulations
+// pub fn monte_carlo_sharpe(avr: f64, std_dev: f64, rf: f64, n_sims: usize) -> Vec<f64> {
+//     // generate random returns, compute Sharpe ratios, return them
+
+//     let mut sim_sharpe_r = Vec::with_capacity(n_sims);
+//     //handle 0 volatility by just returning empty vector since dividng by 0 is not possible
+//     if std_dev == 0.0 {
+//         return sim_sharpe_r;
+//     }
+//     // Standard normal distribution to generate random daily returns
+//     let mut rng = thread_rng();
+//     let ret_dist = Normal::new(avr, std_dev).unwrap();
+
+//     for _ in 0..n_sims {
+//         //sim returns through this range (one year)
+//         let sim_ret: Vec<f64> = (0..TRADING_DAYS_PER_YEAR)
+//             .map(|_| ret_dist.sample(&mut rng))
+//             .collect();
+
+//         //Compute mean & Std dev
+//         if let Some((avr, std_dev)) = calc_stats(&sim_ret) {
+//             //annualized return -> average return per year
+//             let annualized_return = avr * TRADING_DAYS_PER_YEAR as f64;
+//             let annualized_volatility = std_dev * (TRADING_DAYS_PER_YEAR as f64).sqrt();
+//             //sharpe ratio
+//             if annualized_volatility > 0.0 {
+//                 let sharpe = (annualized_return - rf) / annualized_volatility;
+//                 sim_sharpe_r.push(sharpe);
+//             }
+//         }
+//     }
+//     sim_sharpe_r
+// }
+
+// //Beta(volatility and risk) of asset returns compared to market returns
+// pub fn beta(asset_rets: &[f64], market_rets: &[f64]) -> Option<f64> {
+//     //making sure both the csv
+//     if asset_rets.len() != market_rets.len() || asset_rets.len() < 2 {
+//         return None;
+//     }
+//     //covariance btween asset and market returns
+//     let cov = asset_rets.covariance(market_rets);
+//     //variance
+//     let var_market = market_rets.variance();
+//     if var_market == 0.0 {
+//         return None; // so we do not divide by zero
+//     }
+//     //Beta= Cov(asset,market)/var(market)
+//     Some(cov / var_market)
+// }
+// //Alpha (excess returns)
+// pub fn alpha(asset_rets: &[f64], market_rets: &[f64], rf: f64) -> Option<f64> {
+//     //beta
+//     let b = beta(asset_rets, market_rets)?;
+
+//     //mean
+//     let avr_asset = asset_rets.mean();
+//     let avr_market = market_rets.mean();
+
+//     //Alpha = asset mean return - [Rf + Beta * (Market mean - Rf)]
+//     Some(avr_asset - (rf + b * (avr_market - rf)))
+// }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Candle;
+    use chrono::NaiveDate;
+
+    // Helper function to create a Candle easily
+    fn candle(close: f64) -> Candle {
+        Candle {
+            date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 100.0,
+        }
+    }
+
+    // -------------------
+    // Tests for daily_returns
+    // -------------------
+
+    #[test]
+    fn test_daily_returns_empty() {
+        let candles: Vec<Candle> = vec![];
+        let returns = daily_returns(&candles);
+        assert!(returns.is_empty());
+    }
+
+    #[test]
+    fn test_daily_returns_one_candle() {
+        let candles = vec![candle(100.0)];
+        let returns = daily_returns(&candles);
+        assert!(returns.is_empty());
+    }
+
+    #[test]
+    fn test_daily_returns_two_candles() {
+        let candles: Vec<Candle> = vec![candle(100.0), candle(110.0)];
+        let returns = daily_returns(&candles);
+        // Add the f64 suffix to the literals
+        let expected = (110.0_f64 / 100.0_f64).ln();
+        assert_eq!(returns.kind(), ReturnKind::Log);
+        assert_eq!(returns.len(), 1);
+        assert!((returns.values()[0] - expected).abs() < 1e-10);
+    }
+    #[test]
+    fn test_daily_returns_multiple_candles() {
+        let candles: Vec<Candle> = vec![candle(100.0), candle(105.0), candle(110.0)];
+        let returns = daily_returns(&candles);
+
+        // Add the f64 suffix to the literals in the vector
+        let expected: Vec<f64> = vec![(105.0_f64 / 100.0_f64).ln(), (110.0_f64 / 105.0_f64).ln()];
+
+        assert_eq!(returns.len(), expected.len());
+        for (r, e) in returns.values().iter().zip(expected.iter()) {
+            assert!((r - e).abs() < 1e-10);
+        }
+    }
+
+    // -------------------
+    // Tests for calc_stats
+    // -------------------
+
+    #[test]
+    fn test_calc_stats_empty() {
+        let returns: Vec<f64> = vec![];
+        assert!(calc_stats(&returns).is_none());
+    }
+
+    #[test]
+    fn test_calc_stats_single_value() {
+        let returns = vec![0.01];
+        assert!(calc_stats(&returns).is_none());
+    }
+
+    #[test]
+    fn test_calc_stats_known_values() {
+        let returns = vec![0.0, 0.0, 0.0];
+        let (mean, std) = calc_stats(&returns).unwrap();
+        assert!((mean - 0.0).abs() < 1e-10);
+        assert!((std - 0.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_calc_stats_nontrivial_values() {
+        let returns = vec![0.01, 0.02, 0.03];
+        let (mean, std) = calc_stats(&returns).unwrap();
+        let expected_mean = 0.02;
+        let expected_std = ((0.01_f64.powi(2) + 0.0 + 0.01_f64.powi(2)) / 2.0).sqrt();
+        assert!((mean - expected_mean).abs() < 1e-10);
+        assert!((std - expected_std).abs() < 1e-10);
+    }
+
+    // -------------------
+    // Tests for monte_carlo_sharpe
+    // -------------------
+
+    #[test]
+    fn test_monte_carlo_sharpe_basic() {
+        let avr = 0.0005; // 0.05% daily
+        let std_dev = 0.01; // 1% daily
+        let rf = 0.02; // 2% annual
+        let n_sims = 10;
+
+        let sharpe_ratios = monte_carlo_sharpe(avr, std_dev, rf, n_sims);
+
+        // Should produce exactly n_sims results
+        assert_eq!(sharpe_ratios.len(), n_sims);
+
+        // Some ratios should be non-zero
+        assert!(sharpe_ratios.iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_monte_carlo_sharpe_zero_volatility() {
+        let avr = 0.001;
+        let std_dev = 0.0;
+        let rf = 0.01;
+        let n_sims = 5;
+
+        let sharpe_ratios = monte_carlo_sharpe(avr, std_dev, rf, n_sims);
+
+        // All results should be empty because volatility = 0 (division by zero avoided)
+        assert!(sharpe_ratios.is_empty());
+    }
+}
+#[cfg(test)]
+mod beta_alpha_tests {
+    use super::*;
+    use statrs::statistics::Statistics;
+
+    #[test]
+    fn test_beta_basic() {
+        let asset = vec![0.01, 0.02, 0.03, 0.04];
+        let market = vec![0.02, 0.03, 0.04, 0.05];
+
+        let result = beta(&asset, &market).unwrap();
+
+        let cov = asset.as_slice().covariance(market.as_slice());
+        let var_m = market.as_slice().variance();
+        let expected = cov / var_m;
+        assert!((result - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_beta_mismatched_lengths() {
+        let asset = vec![0.01, 0.02];
+        let market = vec![0.01];
+        assert!(beta(&asset, &market).is_none());
+    }
+
+    #[test]
+    fn test_beta_zero_variance() {
+        let asset = vec![0.01, 0.02, 0.03];
+        let market = vec![0.05, 0.05, 0.05]; // zero variance
+        assert!(beta(&asset, &market).is_none());
+    }
+
+    #[test]
+    fn test_alpha_basic() {
+        let asset = vec![0.01, 0.02, 0.03];
+        let market = vec![0.02, 0.03, 0.04];
+        let rf_rets = vec![0.01, 0.01, 0.01]; // constant per-period rf
+
+        let a = alpha(&asset, &market, &rf_rets).unwrap();
+
+        // manual calc
+        let excess_asset: Vec<f64> = asset.iter().zip(&rf_rets).map(|(a, r)| a - r).collect();
+        let excess_market: Vec<f64> = market.iter().zip(&rf_rets).map(|(m, r)| m - r).collect();
+
+        let b = beta(&excess_asset, &excess_market).unwrap();
+        let mean_asset = excess_asset.mean();
+        let mean_market = excess_market.mean();
+        let expected = mean_asset - b * mean_market;
+
+        assert!((a - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_alpha_mismatched_lengths() {
+        let asset = vec![0.01, 0.02];
+        let market = vec![0.01];
+        let rf_rets = vec![0.01, 0.01];
+        assert!(alpha(&asset, &market, &rf_rets).is_none());
+    }
+
+    #[test]
+    fn test_alpha_zero_variance_market() {
+        let asset = vec![0.01, 0.02, 0.03];
+        let market = vec![0.05, 0.05, 0.05]; // zero variance
+        let rf_rets = vec![0.01, 0.01, 0.01];
+        assert!(alpha(&asset, &market, &rf_rets).is_none());
+    }
+
+    #[test]
+    fn test_beta_large_dataset() {
+        let asset: Vec<f64> = (1..=1000).map(|x| x as f64 * 0.001).collect();
+        let market: Vec<f64> = (1..=1000).map(|x| x as f64 * 0.0015).collect();
+
+        let result = beta(&asset, &market).unwrap();
+        let cov = asset.as_slice().covariance(&market);
+        let var_m = market.as_slice().variance();
+        let expected = cov / var_m;
+
+        assert!((result - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_alpha_large_dataset() {
+        let asset: Vec<f64> = (1..=1000).map(|x| (x as f64).sin() * 0.01).collect();
+        let market: Vec<f64> = (1..=1000).map(|x| (x as f64).cos() * 0.01).collect();
+        let rf_rets: Vec<f64> = vec![0.0; 1000]; // zero rf
+
+        let result = alpha(&asset, &market, &rf_rets).unwrap();
+
+        let excess_asset: Vec<f64> = asset.iter().zip(&rf_rets).map(|(a, r)| a - r).collect();
+        let excess_market: Vec<f64> = market.iter().zip(&rf_rets).map(|(m, r)| m - r).collect();
+
+        let mean_asset = excess_asset.as_slice().mean();
+        let mean_market = excess_market.as_slice().mean();
+        let beta_val = beta(&excess_asset, &excess_market).unwrap();
+        let expected = mean_asset - beta_val * mean_market;
+
+        assert!((result - expected).abs() < 1e-10);
+    }
+}
+
+#[cfg(test)]
+mod trade_stats_tests {
+    use super::*;
     use chrono::NaiveDate;
 
-    // Helper function to create a Candle easily
-    fn candle(close: f64) -> Candle {
-        Candle {
-            date: NaiveDate::from_ymd_opt(2025, 9, 1).unwrap(),
-            open: close,
-            high: close,
-            low: close,
-            close,
-            volume: 100.0,
-        }
+    fn trade(pnl: f64, bars_held: usize) -> Trade {
+        let date = NaiveDate::from_ymd_opt(2025, 9, 1).unwrap();
+        Trade {
+            symbol: "XYZ".to_string(),
+            entry_date: date,
+            exit_date: date,
+            entry_price: 10.0,
+            exit_price: 10.0 + pnl / 10.0,
+            qty: 10.0,
+            fees: 0.0,
+            slippage: 0.0,
+            pnl,
+            mae: pnl.min(0.0),
+            mfe: pnl.max(0.0),
+            bars_held,
+        }
+    }
+
+    #[test]
+    fn test_trade_stats_empty() {
+        assert!(trade_stats(&[]).is_none());
+    }
+
+    #[test]
+    fn test_trade_stats_basic() {
+        let trades: Vec<Trade> = [100.0, 50.0, -30.0, -20.0, -10.0, 60.0]
+            .iter()
+            .map(|&pnl| trade(pnl, 4))
+            .collect();
+        let stats = trade_stats(&trades).unwrap();
+
+        assert_eq!(stats.trades, 6);
+        assert_eq!(stats.wins, 3);
+        assert_eq!(stats.losses, 3);
+        assert!((stats.win_rate - 0.5).abs() < 1e-10);
+        assert!((stats.avg_win - 70.0).abs() < 1e-10);
+        assert!((stats.avg_loss + 20.0).abs() < 1e-10);
+        assert!((stats.payoff_ratio - 3.5).abs() < 1e-10);
+        assert!((stats.profit_factor - 3.5).abs() < 1e-10);
+        assert!((stats.expectancy - 25.0).abs() < 1e-10);
+        assert_eq!(stats.longest_win_streak, 2);
+        assert_eq!(stats.longest_loss_streak, 3);
+        assert!((stats.avg_bars_held - 4.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_trade_stats_no_losses() {
+        let trades = vec![trade(10.0, 1), trade(0.0, 1), trade(5.0, 1)];
+        let stats = trade_stats(&trades).unwrap();
+
+        assert_eq!(stats.wins, 2);
+        assert_eq!(stats.losses, 0);
+        assert!(stats.profit_factor.is_infinite());
+        // the breakeven trade breaks the streak
+        assert_eq!(stats.longest_win_streak, 1);
+    }
+
+    #[test]
+    fn test_kelly_fraction() {
+        // 60% winners paying twice the average loss: 0.6 - 0.4 / 2
+        let pnls = [20.0, 20.0, 20.0, -10.0, -10.0];
+        assert!((kelly_fraction(&pnls).unwrap() - 0.4).abs() < 1e-12);
+        // no edge
+        assert!(kelly_fraction(&[10.0, -10.0, -10.0]).unwrap() < 0.0);
+        assert_eq!(kelly_fraction(&[5.0, 0.0]), Some(0.5));
+        assert!(kelly_fraction(&[]).is_none());
+    }
+}
+
+#[cfg(test)]
+mod drawdown_tests {
+    use super::*;
+    use crate::test_util::dates;
+
+    #[test]
+    fn test_equity_from_returns() {
+        let log = Returns::new(ReturnKind::Log, vec![0.1_f64.ln_1p(), (-0.5_f64).ln_1p()]);
+        let equity = equity_from_returns(&log).unwrap();
+        assert_eq!(equity.len(), 3);
+        assert!((equity[1] - 1.1).abs() < 1e-10);
+        assert!((equity[2] - 0.55).abs() < 1e-10);
+        assert!(equity_from_returns(&log.to_simple().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_underwater() {
+        let uw = underwater(&[100.0, 110.0, 99.0, 121.0]);
+        let expected = [0.0, 0.0, -0.1, 0.0];
+        for (u, e) in uw.iter().zip(expected) {
+            assert!((u - e).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_drawdown_episodes() {
+        let equity = [100.0, 90.0, 80.0, 100.0, 95.0, 105.0, 70.0, 80.0];
+        let d = dates(equity.len());
+        let episodes = drawdowns(&d, &equity);
+
+        assert_eq!(episodes.len(), 3);
+        assert_eq!(episodes[0].peak_date, d[0]);
+        assert_eq!(episodes[0].trough_date, d[2]);
+        assert_eq!(episodes[0].recovery_date, Some(d[3]));
+        assert!((episodes[0].depth + 0.2).abs() < 1e-10);
+        assert_eq!(episodes[0].length, 3);
+
+        // last one never recovers
+        assert_eq!(episodes[2].recovery_date, None);
+        assert_eq!(episodes[2].length, 2);
+
+        let max = max_drawdown(&d, &equity).unwrap();
+        assert!((max.depth - (70.0 / 105.0 - 1.0)).abs() < 1e-10);
+        assert_eq!(longest_drawdown(&d, &equity).unwrap().peak_date, d[0]);
+
+        let top = top_drawdowns(&d, &equity, 2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0], max);
+        assert_eq!(top[1].peak_date, d[0]);
+    }
+
+    #[test]
+    fn test_no_drawdown() {
+        let equity = [1.0, 2.0, 3.0];
+        assert!(drawdowns(&dates(3), &equity).is_empty());
+        assert!(calmar_ratio(&equity).is_none());
+        assert_eq!(ulcer_index(&equity), Some(0.0));
+    }
+
+    #[test]
+    fn test_calmar_and_ulcer() {
+        // one year of flat equity after a 20% dip, ending up 10%
+        let mut equity = vec![100.0, 80.0];
+        equity.resize(TRADING_DAYS_PER_YEAR, 100.0);
+        equity.push(110.0);
+
+        let calmar = calmar_ratio(&equity).unwrap();
+        assert!((calmar - 0.1 / 0.2).abs() < 1e-10);
+
+        let ulcer = ulcer_index(&equity).unwrap();
+        let expected = (400.0 / equity.len() as f64).sqrt();
+        assert!((ulcer - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_drawdowns_mismatched_lengths() {
+        assert!(drawdowns(&dates(2), &[1.0, 0.5, 0.7]).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod risk_ratio_tests {
+    use super::*;
+
+    const RETS: [f64; 6] = [0.01, -0.02, 0.015, 0.005, -0.01, 0.02];
+
+    fn simple() -> Returns {
+        Returns::new(ReturnKind::Simple, RETS.to_vec())
+    }
+
+    #[test]
+    fn test_sharpe_ratio() {
+        let rf = vec![0.0001; RETS.len()];
+        let sharpe = sharpe_ratio(&simple(), &rf).unwrap();
+
+        let excess: Vec<f64> = RETS.iter().map(|r| r - 0.0001).collect();
+        let (mean, std) = calc_stats(&excess).unwrap();
+        let expected = mean / std * (TRADING_DAYS_PER_YEAR as f64).sqrt();
+        assert!((sharpe - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_sharpe_ratio_mismatched_lengths() {
+        assert!(sharpe_ratio(&simple(), &[0.0; 3]).is_none());
+    }
+
+    #[test]
+    fn test_sharpe_ratio_rejects_log_returns() {
+        let log = Returns::new(ReturnKind::Log, RETS.to_vec());
+        assert!(sharpe_ratio(&log, &[0.0; RETS.len()]).is_none());
+    }
+
+    #[test]
+    fn test_sortino_ratio() {
+        let sortino = sortino_ratio(&simple(), 0.0).unwrap();
+
+        let mean = RETS.iter().sum::<f64>() / 6.0;
+        let downside = ((0.02_f64.powi(2) + 0.01_f64.powi(2)) / 6.0).sqrt();
+        let expected = mean / downside * (TRADING_DAYS_PER_YEAR as f64).sqrt();
+        assert!((sortino - expected).abs() < 1e-10);
+
+        // no returns below the target -> undefined
+        let gains = Returns::new(ReturnKind::Simple, vec![0.01, 0.02]);
+        assert!(sortino_ratio(&gains, 0.0).is_none());
+        let log = Returns::new(ReturnKind::Log, RETS.to_vec());
+        assert!(sortino_ratio(&log, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_omega_ratio() {
+        let omega = omega_ratio(&simple(), 0.0).unwrap();
+        assert!((omega - 0.05 / 0.03).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_treynor_ratio() {
+        let market: Vec<f64> = RETS.iter().map(|r| r / 2.0).collect();
+        let market = Returns::new(ReturnKind::Simple, market);
+        let rf = vec![0.0; RETS.len()];
+        let treynor = treynor_ratio(&simple(), &market, &rf).unwrap();
+
+        // beta of 2
+        let expected = RETS.iter().sum::<f64>() / 6.0 * TRADING_DAYS_PER_YEAR as f64 / 2.0;
+        assert!((treynor - expected).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_tracking_error_and_information_ratio() {
+        let bench = Returns::new(ReturnKind::Simple, vec![0.0; RETS.len()]);
+        let (mean, std) = calc_stats(&RETS).unwrap();
+        let days = TRADING_DAYS_PER_YEAR as f64;
+
+        let te = tracking_error(&simple(), &bench).unwrap();
+        assert!((te - std * days.sqrt()).abs() < 1e-10);
+
+        let ir = information_ratio(&simple(), &bench).unwrap();
+        assert!((ir - mean * days / te).abs() < 1e-10);
+
+        let log_bench = bench.to_log().unwrap();
+        assert!(tracking_error(&simple(), &log_bench).is_none());
+    }
+
+    #[test]
+    fn test_tail_ratio() {
+        let rets: Vec<f64> = (-50..=50).map(|i| i as f64 / 1000.0).collect();
+        let tail = tail_ratio(&Returns::new(ReturnKind::Simple, rets)).unwrap();
+        assert!((tail - 1.0).abs() < 1e-10);
+    }
+}
+
+#[cfg(test)]
+mod var_tests {
+    use super::*;
+
+    fn log(values: &[f64]) -> Returns {
+        Returns::new(ReturnKind::Log, values.to_vec())
     }
 
-    // -------------------
-    // Tests for daily_returns
-    // -------------------
+    // -0.050, -0.049, ..., 0.049, 0.050
+    fn uniform_returns() -> Returns {
+        log(&(-50..=50).map(|i| i as f64 / 1000.0).collect::<Vec<f64>>())
+    }
 
     #[test]
-    fn test_daily_returns_empty() {
-        let candles: Vec<Candle> = vec![];
-        let returns = daily_returns(&candles);
-        assert!(returns.is_empty());
+    fn test_historical_var() {
+        let est = historical_var(&uniform_returns(), 0.95, 1).unwrap();
+        assert!((est.var - 0.045).abs() < 1e-10);
+        // mean of -0.050..=-0.045
+        assert!((est.cvar - 0.0475).abs() < 1e-10);
     }
 
     #[test]
-    fn test_daily_returns_one_candle() {
-        let candles = vec![candle(100.0)];
-        let returns = daily_returns(&candles);
-        assert!(returns.is_empty());
+    fn test_historical_var_horizon() {
+        let rets = log(&[-0.01, -0.02, 0.03, -0.04]);
+        // two-day sums: -0.03, 0.01, -0.01
+        let est = historical_var(&rets, 0.99, 2).unwrap();
+        assert!((est.var - (0.03 - 0.02 * 0.02)).abs() < 1e-10);
+        assert!((est.cvar - 0.03).abs() < 1e-10);
     }
 
     #[test]
-    fn test_daily_returns_two_candles() {
-        let candles: Vec<Candle> = vec![candle(100.0), candle(110.0)];
-        let returns: Vec<f64> = daily_returns(&candles);
-        // Add the f64 suffix to the literals
-        let expected = (110.0_f64 / 100.0_f64).ln();
-        assert_eq!(returns.len(), 1);
-        assert!((returns[0] - expected).abs() < 1e-10);
+    fn test_var_invalid_inputs() {
+        let rets = uniform_returns();
+        assert!(historical_var(&rets, 1.0, 1).is_none());
+        assert!(historical_var(&rets, 0.95, 0).is_none());
+        assert!(parametric_var(&log(&[0.01]), 0.95, 1).is_none());
     }
+
     #[test]
-    fn test_daily_returns_multiple_candles() {
-        let candles: Vec<Candle> = vec![candle(100.0), candle(105.0), candle(110.0)];
-        let returns: Vec<f64> = daily_returns(&candles);
+    fn test_var_rejects_simple_returns() {
+        let simple = uniform_returns().to_simple().unwrap();
+        assert!(historical_var(&simple, 0.95, 1).is_none());
+        assert!(parametric_var(&simple, 0.95, 1).is_none());
+        assert!(cornish_fisher_var(&simple, 0.95, 1).is_none());
+        assert!(monte_carlo_var(&simple, 0.95, 1, 100).is_none());
+        assert!(rolling_var(&simple, 0.95, 50).is_none());
+    }
 
-        // Add the f64 suffix to the literals in the vector
-        let expected: Vec<f64> = vec![(105.0_f64 / 100.0_f64).ln(), (110.0_f64 / 105.0_f64).ln()];
+    #[test]
+    fn test_parametric_var() {
+        let rets = uniform_returns();
+        let (mean, std) = calc_stats(rets.values()).unwrap();
+        let est = parametric_var(&rets, 0.99, 4).unwrap();
+
+        let z = -2.3263478740408408;
+        let expected = -(mean * 4.0 + z * std * 2.0);
+        assert!((est.var - expected).abs() < 1e-8);
+        assert!(est.cvar > est.var);
+    }
 
-        assert_eq!(returns.len(), expected.len());
-        for (r, e) in returns.iter().zip(expected.iter()) {
-            assert!((r - e).abs() < 1e-10);
-        }
+    #[test]
+    fn test_cornish_fisher_reduces_to_normal() {
+        let z = -1.6448536269514722;
+        assert_eq!(cornish_fisher_z(z, 0.0, 0.0), z);
+        // negative skew pushes the left tail further out
+        assert!(cornish_fisher_z(z, -1.0, 0.0) < z);
     }
 
-    // -------------------
-    // Tests for calc_stats
-    // -------------------
+    #[test]
+    fn test_cornish_fisher_var() {
+        let rets = uniform_returns();
+        let cf = cornish_fisher_var(&rets, 0.99, 1).unwrap();
+        let normal = parametric_var(&rets, 0.99, 1).unwrap();
+
+        // uniform data has thin tails, so the adjusted 99% VaR is smaller
+        assert!(cf.var < normal.var);
+        assert!(cf.cvar > cf.var);
+    }
 
     #[test]
-    fn test_calc_stats_empty() {
-        let returns: Vec<f64> = vec![];
-        assert!(calc_stats(&returns).is_none());
+    fn test_monte_carlo_var_close_to_parametric() {
+        let rets = uniform_returns();
+        let mc = monte_carlo_var(&rets, 0.95, 1, 50_000).unwrap();
+        let normal = parametric_var(&rets, 0.95, 1).unwrap();
+        assert!((mc.var - normal.var).abs() < 0.003);
+        assert!((mc.cvar - normal.cvar).abs() < 0.003);
     }
 
     #[test]
-    fn test_calc_stats_single_value() {
-        let returns = vec![0.01];
-        assert!(calc_stats(&returns).is_none());
+    fn test_var_exceptions() {
+        let rets = Returns::new(ReturnKind::Log, vec![-0.05, -0.03, 0.01, -0.01]);
+        // forecasts for the last three days only
+        let exc = var_exceptions(&rets, &[0.02, 0.02, 0.02]).unwrap();
+        assert_eq!(exc, vec![true, false, false]);
+        assert!(var_exceptions(&rets, &[0.02; 5]).is_none());
+        let simple = Returns::new(ReturnKind::Simple, rets.values().to_vec());
+        assert!(var_exceptions(&simple, &[0.02]).is_none());
     }
 
     #[test]
-    fn test_calc_stats_known_values() {
-        let returns = vec![0.0, 0.0, 0.0];
-        let (mean, std) = calc_stats(&returns).unwrap();
-        assert!((mean - 0.0).abs() < 1e-10);
-        assert!((std - 0.0).abs() < 1e-10);
+    fn test_rolling_var_uses_only_prior_returns() {
+        let rets: Vec<f64> = (0..400)
+            .map(|i| ((i * 37) % 101 - 50) as f64 / 1000.0)
+            .collect();
+        let forecasts = rolling_var(&log(&rets), 0.95, 250).unwrap();
+        assert_eq!(forecasts.len(), rets.len() - 250);
+        for (i, var) in forecasts.iter().enumerate() {
+            let fitted = historical_var(&log(&rets[i..i + 250]), 0.95, 1).unwrap();
+            assert_eq!(*var, fitted.var);
+        }
+
+        // a crash on the forecast day cannot move that day's forecast
+        let mut crashed = rets.clone();
+        crashed[250] = -0.5;
+        assert_eq!(
+            rolling_var(&log(&crashed), 0.95, 250).unwrap()[0],
+            forecasts[0]
+        );
+        assert!(rolling_var(&log(&rets[..250]), 0.95, 250).is_none());
     }
 
     #[test]
-    fn test_calc_stats_nontrivial_values() {
-        let returns = vec![0.01, 0.02, 0.03];
-        let (mean, std) = calc_stats(&returns).unwrap();
-        let expected_mean = 0.02;
-        let expected_std = ((0.01_f64.powi(2) + 0.0 + 0.01_f64.powi(2)) / 2.0).sqrt();
-        assert!((mean - expected_mean).abs() < 1e-10);
-        assert!((std - expected_std).abs() < 1e-10);
+    fn test_kupiec_test() {
+        // exactly the expected 5% exception rate
+        let exc: Vec<bool> = (0..100).map(|i| i % 20 == 0).collect();
+        let test = kupiec_test(&exc, 0.95).unwrap();
+        assert_eq!(test.exceptions, 5);
+        assert!(test.statistic.abs() < 1e-10);
+        assert!((test.p_value - 1.0).abs() < 1e-10);
+
+        // far too many exceptions
+        let exc: Vec<bool> = (0..100).map(|i| i % 4 == 0).collect();
+        assert!(kupiec_test(&exc, 0.95).unwrap().p_value < 0.01);
     }
 
-    // -------------------
-    // Tests for monte_carlo_sharpe
-    // -------------------
+    #[test]
+    fn test_christoffersen_test() {
+        let spread: Vec<bool> = (0..100).map(|i| i % 10 == 0).collect();
+        let clustered: Vec<bool> = (0..100).map(|i| i < 10).collect();
+
+        let spread = christoffersen_test(&spread).unwrap();
+        let clustered = christoffersen_test(&clustered).unwrap();
+        assert_eq!(spread.exceptions, clustered.exceptions);
+        assert!(clustered.p_value < 0.01);
+        assert!(spread.p_value > clustered.p_value);
+    }
+}
+
+#[cfg(test)]
+mod bootstrap_tests {
+    use super::*;
+
+    fn series() -> Vec<f64> {
+        (0..20).map(|i| i as f64 / 1000.0).collect()
+    }
+
+    // index of each resampled value in the original series
+    fn positions(path: &[f64]) -> Vec<usize> {
+        path.iter().map(|r| (r * 1000.0).round() as usize).collect()
+    }
 
     #[test]
-    fn test_monte_carlo_sharpe_basic() {
-        let avr = 0.0005; // 0.05% daily
-        let std_dev = 0.01; // 1% daily
-        let rf = 0.02; // 2% annual
-        let n_sims = 10;
+    fn test_resample_iid() {
+        let rets = series();
+        let path = resample(&rets, Resampling::Iid, &mut thread_rng());
+        assert_eq!(path.len(), rets.len());
+        assert!(path.iter().all(|r| rets.contains(r)));
+    }
 
-        let sharpe_ratios = monte_carlo_sharpe(avr, std_dev, rf, n_sims);
+    #[test]
+    fn test_resample_circular_keeps_blocks() {
+        let rets = series();
+        let path = resample(&rets, Resampling::Circular { block: 5 }, &mut thread_rng());
+        assert_eq!(path.len(), rets.len());
+
+        for block in positions(&path).chunks(5) {
+            for w in block.windows(2) {
+                assert_eq!(w[1], (w[0] + 1) % rets.len());
+            }
+        }
+    }
 
-        // Should produce exactly n_sims results
-        assert_eq!(sharpe_ratios.len(), n_sims);
+    #[test]
+    fn test_resample_stationary_long_blocks_rotate() {
+        let rets = series();
+        // a mean block far longer than the series almost never restarts
+        let method = Resampling::Stationary { mean_block: 1e12 };
+        let pos = positions(&resample(&rets, method, &mut thread_rng()));
+        for w in pos.windows(2) {
+            assert_eq!(w[1], (w[0] + 1) % rets.len());
+        }
+    }
 
-        // Some ratios should be non-zero
-        assert!(sharpe_ratios.iter().any(|&s| s != 0.0));
+    #[test]
+    fn test_resample_empty() {
+        assert!(resample(&[], Resampling::Iid, &mut thread_rng()).is_empty());
     }
 
     #[test]
-    fn test_monte_carlo_sharpe_zero_volatility() {
-        let avr = 0.001;
-        let std_dev = 0.0;
-        let rf = 0.01;
-        let n_sims = 5;
+    fn test_bootstrap_distributions() {
+        let rets: Vec<f64> = (0..100).map(|i| ((i % 7) as f64 - 3.0) / 100.0).collect();
+        let rets = Returns::new(ReturnKind::Simple, rets);
+        let rf = vec![0.0; rets.len()];
+        let result = bootstrap(&rets, &rf, Resampling::Circular { block: 10 }, 50).unwrap();
+
+        assert_eq!(result.sharpe.len(), 50);
+        assert_eq!(result.max_drawdown.len(), 50);
+        assert!(result.max_drawdown.iter().all(|d| *d <= 0.0));
+        assert!(result.terminal_wealth.iter().all(|w| *w > 0.0));
+
+        let one_day = Returns::new(ReturnKind::Simple, vec![0.01]);
+        assert!(bootstrap(&one_day, &[0.0], Resampling::Iid, 10).is_none());
+        let log = Returns::new(ReturnKind::Log, rets.values().to_vec());
+        assert!(bootstrap(&log, &rf, Resampling::Iid, 10).is_none());
+    }
 
-        let sharpe_ratios = monte_carlo_sharpe(avr, std_dev, rf, n_sims);
+    #[test]
+    fn test_bootstrap_sharpe_matches_realized() {
+        // a single block covering the whole series only rotates it, which
+        // leaves the Sharpe ratio unchanged
+        let rets: Vec<f64> = (0..60).map(|i| ((i % 7) as f64 - 2.0) / 100.0).collect();
+        let rf: Vec<f64> = (0..60).map(|i| if i < 30 { 0.0 } else { 0.002 }).collect();
+        let rets = Returns::new(ReturnKind::Simple, rets);
+        let realized = sharpe_ratio(&rets, &rf).unwrap();
+
+        let method = Resampling::Circular { block: 60 };
+        let result = bootstrap_seeded(&rets, &rf, method, &Simulation::new(20, 3)).unwrap();
+        assert_eq!(result.sharpe.len(), 20);
+        for sharpe in result.sharpe {
+            assert!((sharpe - realized).abs() < 1e-9);
+        }
+    }
 
-        // All results should be empty because volatility = 0 (division by zero avoided)
-        assert!(sharpe_ratios.is_empty());
+    #[test]
+    fn test_confidence_interval() {
+        let samples: Vec<f64> = (0..=100).map(|i| i as f64).collect();
+        let (lo, hi) = confidence_interval(&samples, 0.9).unwrap();
+        assert!((lo - 5.0).abs() < 1e-10);
+        assert!((hi - 95.0).abs() < 1e-10);
+        assert!(confidence_interval(&samples, 1.0).is_none());
     }
 }
+
 #[cfg(test)]
-mod beta_alpha_tests {
+mod simulation_tests {
     use super::*;
-    use statrs::statistics::Statistics;
 
     #[test]
-    fn test_beta_basic() {
-        let asset = vec![0.01, 0.02, 0.03, 0.04];
-        let market = vec![0.02, 0.03, 0.04, 0.05];
+    fn test_same_seed_same_sharpe() {
+        let sim = Simulation::new(20, 42);
+        let a = monte_carlo_sharpe_seeded(0.0005, 0.01, 0.02, &sim);
+        let b = monte_carlo_sharpe_seeded(0.0005, 0.01, 0.02, &sim);
+        assert_eq!(a, b);
+
+        let c = monte_carlo_sharpe_seeded(0.0005, 0.01, 0.02, &Simulation::new(20, 43));
+        assert_ne!(a, c);
+    }
 
-        let result = beta(&asset, &market).unwrap();
+    #[test]
+    fn test_results_independent_of_thread_count() {
+        let sim = Simulation::new(PARALLEL_MIN_SIMS + 37, 7);
+        let single = monte_carlo_sharpe_seeded(0.0005, 0.01, 0.02, &sim.with_threads(1));
+        let multi = monte_carlo_sharpe_seeded(0.0005, 0.01, 0.02, &sim.with_threads(5));
+        assert_eq!(single.len(), sim.n_sims);
+        assert_eq!(single, multi);
+    }
 
-        let cov = asset.as_slice().covariance(market.as_slice());
-        let var_m = market.as_slice().variance();
-        let expected = cov / var_m;
-        assert!((result - expected).abs() < 1e-10);
+    #[test]
+    fn test_seeded_bootstrap_and_var() {
+        let rets: Vec<f64> = (0..50).map(|i| ((i % 5) as f64 - 2.0) / 100.0).collect();
+        let method = Resampling::Stationary { mean_block: 5.0 };
+        let sim = Simulation::new(PARALLEL_MIN_SIMS, 11);
+
+        let simple = Returns::new(ReturnKind::Simple, rets.clone());
+        let rf = vec![0.0; rets.len()];
+        let a = bootstrap_seeded(&simple, &rf, method, &sim.with_threads(1)).unwrap();
+        let b = bootstrap_seeded(&simple, &rf, method, &sim.with_threads(3)).unwrap();
+        assert_eq!(a.terminal_wealth, b.terminal_wealth);
+        assert_eq!(a.max_drawdown, b.max_drawdown);
+
+        let rets = Returns::new(ReturnKind::Log, rets);
+        let v1 = monte_carlo_var_seeded(&rets, 0.95, 1, &sim.with_threads(1));
+        let v2 = monte_carlo_var_seeded(&rets, 0.95, 1, &sim.with_threads(4));
+        assert_eq!(v1, v2);
     }
 
     #[test]
-    fn test_beta_mismatched_lengths() {
-        let asset = vec![0.01, 0.02];
-        let market = vec![0.01];
-        assert!(beta(&asset, &market).is_none());
+    fn test_simulation_from_rng() {
+        let a = Simulation::from_rng(10, &mut StdRng::seed_from_u64(1));
+        let b = Simulation::from_rng(10, &mut StdRng::seed_from_u64(1));
+        assert_eq!(a, b);
+    }
+}
+
+#[cfg(test)]
+mod deflated_sharpe_tests {
+    use super::*;
+
+    fn returns() -> Returns {
+        let values = (0..500)
+            .map(|i| 0.001 + 0.01 * ((i * 37 % 101) as f64 / 50.0 - 1.0))
+            .collect();
+        Returns::new(ReturnKind::Simple, values)
     }
 
     #[test]
-    fn test_beta_zero_variance() {
-        let asset = vec![0.01, 0.02, 0.03];
-        let market = vec![0.05, 0.05, 0.05]; // zero variance
-        assert!(beta(&asset, &market).is_none());
+    fn test_psr_against_own_sharpe_is_half() {
+        let rets = returns();
+        let sharpe = per_period_sharpe(&rets).unwrap();
+        let psr = probabilistic_sharpe_ratio(&rets, sharpe).unwrap();
+        assert!((psr - 0.5).abs() < 1e-10);
+
+        // excess returns are accepted too, log returns are not
+        let excess = rets.to_excess(&vec![0.0; rets.len()]).unwrap();
+        assert_eq!(probabilistic_sharpe_ratio(&excess, sharpe), Some(psr));
+        let log = rets.to_log().unwrap();
+        assert!(probabilistic_sharpe_ratio(&log, sharpe).is_none());
     }
 
     #[test]
-    fn test_alpha_basic() {
-        let asset = vec![0.01, 0.02, 0.03];
-        let market = vec![0.02, 0.03, 0.04];
-        let rf_rets = vec![0.01, 0.01, 0.01]; // constant per-period rf
+    fn test_psr_increases_with_lower_benchmark() {
+        let rets = returns();
+        let low = probabilistic_sharpe_ratio(&rets, -0.1).unwrap();
+        let zero = probabilistic_sharpe_ratio(&rets, 0.0).unwrap();
+        assert!(low > zero);
+        assert!(zero > 0.5);
+    }
 
-        let a = alpha(&asset, &market, &rf_rets).unwrap();
+    #[test]
+    fn test_expected_max_sharpe() {
+        assert!(expected_max_sharpe(1, 0.01).is_none());
+        let few = expected_max_sharpe(10, 0.01).unwrap();
+        let many = expected_max_sharpe(1000, 0.01).unwrap();
+        assert!(few > 0.0);
+        assert!(many > few);
+        assert_eq!(expected_max_sharpe(10, 0.0), Some(0.0));
+    }
 
-        // manual calc
-        let excess_asset: Vec<f64> = asset.iter().zip(&rf_rets).map(|(a, r)| a - r).collect();
-        let excess_market: Vec<f64> = market.iter().zip(&rf_rets).map(|(m, r)| m - r).collect();
+    #[test]
+    fn test_dsr_penalizes_many_trials() {
+        let rets = returns();
+        let psr = probabilistic_sharpe_ratio(&rets, 0.0).unwrap();
+        let trials: Vec<f64> = (0..50).map(|i| (i as f64 - 25.0) / 500.0).collect();
+        let known: Vec<Option<f64>> = trials.iter().copied().map(Some).collect();
+        let dsr = deflated_sharpe_ratio(&rets, &known).unwrap();
+        assert!(dsr < psr);
+        assert!(deflated_sharpe_ratio(&rets, &[Some(0.1)]).is_none());
+
+        // trials without a Sharpe still count as tries
+        let mut with_missing = known.clone();
+        with_missing.extend([None; 50]);
+        let deflated = deflated_sharpe_ratio(&rets, &with_missing).unwrap();
+        let threshold = expected_max_sharpe(100, trials.variance()).unwrap();
+        let expected = probabilistic_sharpe_ratio(&rets, threshold).unwrap();
+        assert_eq!(deflated, expected);
+        assert!(deflated < dsr);
+        assert!(deflated_sharpe_ratio(&rets, &[Some(0.1), None]).is_none());
+    }
+}
 
-        let b = beta(&excess_asset, &excess_market).unwrap();
-        let mean_asset = excess_asset.mean();
-        let mean_market = excess_market.mean();
-        let expected = mean_asset - b * mean_market;
+#[cfg(test)]
+mod rolling_tests {
+    use super::*;
+    use crate::test_util::dates;
 
-        assert!((a - expected).abs() < 1e-10);
+    #[test]
+    fn test_rolling_volatility_windows() {
+        let values = [0.01, -0.01, 0.02, -0.02, 0.0];
+        let rets = Returns::new(ReturnKind::Simple, values.to_vec());
+        let d = dates(rets.len());
+        let vol = rolling_volatility(&d, &rets, 3).unwrap();
+        assert_eq!(vol.len(), 3);
+        assert_eq!(vol.dates[0], d[2]);
+        let (_, sd) = calc_stats(&values[1..4]).unwrap();
+        assert!((vol.get(d[3]).unwrap() - annualize_vol(sd)).abs() < 1e-12);
+
+        assert!(rolling_volatility(&d, &rets, 1).is_none());
+        assert!(rolling_volatility(&d[1..], &rets, 3).is_none());
+        assert!(rolling_volatility(&d, &rets, 10).unwrap().is_empty());
     }
 
     #[test]
-    fn test_alpha_mismatched_lengths() {
-        let asset = vec![0.01, 0.02];
-        let market = vec![0.01];
-        let rf_rets = vec![0.01, 0.01];
-        assert!(alpha(&asset, &market, &rf_rets).is_none());
+    fn test_rolling_beta_alpha_correlation() {
+        let market = [0.01, -0.02, 0.015, 0.005, -0.01, 0.02];
+        let asset: Vec<f64> = market.iter().map(|m| 0.001 + 2.0 * m).collect();
+        let asset = Returns::new(ReturnKind::Simple, asset);
+        let market = Returns::new(ReturnKind::Simple, market.to_vec());
+        let rf = vec![0.0; market.len()];
+        let d = dates(market.len());
+
+        let betas = rolling_beta(&d, &asset, &market, 4).unwrap();
+        assert_eq!(betas.len(), 3);
+        assert!(betas.values.iter().all(|b| (b - 2.0).abs() < 1e-10));
+
+        let alphas = rolling_alpha(&d, &asset, &market, &rf, 4).unwrap();
+        assert!(alphas.values.iter().all(|a| (a - 0.001).abs() < 1e-12));
+
+        let corr = rolling_correlation(&d, &asset, &market, 4).unwrap();
+        assert!(corr.values.iter().all(|c| (c - 1.0).abs() < 1e-10));
+
+        let log_market = market.to_log().unwrap();
+        assert!(rolling_beta(&d, &asset, &log_market, 4).is_none());
     }
 
     #[test]
-    fn test_alpha_zero_variance_market() {
-        let asset = vec![0.01, 0.02, 0.03];
-        let market = vec![0.05, 0.05, 0.05]; // zero variance
-        let rf_rets = vec![0.01, 0.01, 0.01];
-        assert!(alpha(&asset, &market, &rf_rets).is_none());
+    fn test_rolling_sharpe_detects_decay() {
+        // a steady edge that turns into steady losses
+        let rets: Vec<f64> = (0..40)
+            .map(|i| if i < 20 { 0.002 } else { -0.002 } + if i % 2 == 0 { 0.01 } else { -0.01 })
+            .collect();
+        let rf = vec![0.0; rets.len()];
+        let d = dates(rets.len());
+        let rets = Returns::new(ReturnKind::Simple, rets);
+        let sharpe = rolling_sharpe(&d, &rets, &rf, 10).unwrap();
+        assert!(sharpe.values[0] > 0.0);
+        assert!(*sharpe.values.last().unwrap() < 0.0);
     }
 
     #[test]
-    fn test_beta_large_dataset() {
-        let asset: Vec<f64> = (1..=1000).map(|x| x as f64 * 0.001).collect();
-        let market: Vec<f64> = (1..=1000).map(|x| x as f64 * 0.0015).collect();
+    fn test_rolling_max_drawdown() {
+        let rets = Returns::new(ReturnKind::Log, vec![0.1, -0.1, -0.1, 0.2, 0.1]);
+        let d = dates(rets.len());
+        let dd = rolling_max_drawdown(&d, &rets, 3).unwrap();
+        assert!((dd.values[0] - ((-0.2f64).exp() - 1.0)).abs() < 1e-12);
+        assert!((dd.values[1] - ((-0.2f64).exp() - 1.0)).abs() < 1e-12);
+        assert!((dd.values[2] - ((-0.1f64).exp() - 1.0)).abs() < 1e-12);
+    }
+}
 
-        let result = beta(&asset, &market).unwrap();
-        let cov = asset.as_slice().covariance(&market);
-        let var_m = market.as_slice().variance();
-        let expected = cov / var_m;
+#[cfg(test)]
+mod distribution_tests {
+    use super::*;
 
-        assert!((result - expected).abs() < 1e-10);
+    fn normal_returns(n: usize, seed: u64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let dist = Normal::new(0.0, 0.01).unwrap();
+        (0..n).map(|_| dist.sample(&mut rng)).collect()
     }
 
     #[test]
-    fn test_alpha_large_dataset() {
-        let asset: Vec<f64> = (1..=1000).map(|x| (x as f64).sin() * 0.01).collect();
-        let market: Vec<f64> = (1..=1000).map(|x| (x as f64).cos() * 0.01).collect();
-        let rf_rets: Vec<f64> = vec![0.0; 1000]; // zero rf
+    fn test_moments() {
+        let symmetric = [-2.0, -1.0, 0.0, 1.0, 2.0];
+        assert!(skewness(&symmetric).unwrap().abs() < 1e-12);
+        // m2 = 2, m4 = 6.8
+        assert!((excess_kurtosis(&symmetric).unwrap() - (6.8 / 4.0 - 3.0)).abs() < 1e-12);
+        assert!(skewness(&[1.0, 1.0, 1.0]).is_none());
+
+        let right_tail = [0.0, 0.0, 0.0, 0.0, 10.0];
+        assert!(skewness(&right_tail).unwrap() > 0.0);
+    }
 
-        let result = alpha(&asset, &market, &rf_rets).unwrap();
+    #[test]
+    fn test_jarque_bera() {
+        let normal = jarque_bera(&normal_returns(2000, 1)).unwrap();
+        assert!(normal.p_value > 0.01);
 
-        let excess_asset: Vec<f64> = asset.iter().zip(&rf_rets).map(|(a, r)| a - r).collect();
-        let excess_market: Vec<f64> = market.iter().zip(&rf_rets).map(|(m, r)| m - r).collect();
+        let mut fat = normal_returns(2000, 2);
+        for r in fat.iter_mut().step_by(50) {
+            *r *= 10.0;
+        }
+        let fat = jarque_bera(&fat).unwrap();
+        assert!(fat.p_value < 1e-6);
+        assert!(fat.statistic > normal.statistic);
+    }
 
-        let mean_asset = excess_asset.as_slice().mean();
-        let mean_market = excess_market.as_slice().mean();
-        let beta_val = beta(&excess_asset, &excess_market).unwrap();
-        let expected = mean_asset - beta_val * mean_market;
+    #[test]
+    fn test_autocorrelation_and_ljung_box() {
+        let alternating: Vec<f64> = (0..100)
+            .map(|i| if i % 2 == 0 { 0.01 } else { -0.01 })
+            .collect();
+        assert!((autocorrelation(&alternating, 1).unwrap() + 0.99).abs() < 1e-12);
+        assert!((autocorrelation(&alternating, 2).unwrap() - 0.98).abs() < 1e-12);
+        assert!(autocorrelation(&alternating, 0).is_none());
+        assert!(autocorrelation(&alternating, 100).is_none());
 
-        assert!((result - expected).abs() < 1e-10);
+        assert!(ljung_box(&alternating, 5).unwrap().p_value < 1e-6);
+        assert!(ljung_box(&normal_returns(1000, 3), 10).unwrap().p_value > 0.01);
+    }
+
+    #[test]
+    fn test_hurst_exponent() {
+        let random = hurst_exponent(&normal_returns(4096, 4)).unwrap();
+        assert!((0.4..0.65).contains(&random));
+
+        // a random walk's levels are extremely persistent
+        let walk: Vec<f64> = normal_returns(4096, 5)
+            .iter()
+            .scan(0.0, |s, r| {
+                *s += r;
+                Some(*s)
+            })
+            .collect();
+        assert!(hurst_exponent(&walk).unwrap() > 0.9);
+
+        assert!(hurst_exponent(&normal_returns(20, 6)).is_none());
+    }
+}
+
+#[cfg(test)]
+mod return_kind_tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions_round_trip() {
+        let prices = [100.0, 110.0, 99.0, 120.0];
+        let simple = Returns::from_prices(&prices, ReturnKind::Simple).unwrap();
+        let log = Returns::from_prices(&prices, ReturnKind::Log).unwrap();
+        assert!((simple.values()[0] - 0.1).abs() < 1e-12);
+
+        let converted = simple.to_log().unwrap();
+        assert_eq!(converted.kind(), ReturnKind::Log);
+        for (a, b) in converted.values().iter().zip(log.values()) {
+            assert!((a - b).abs() < 1e-12);
+        }
+        for (a, b) in log
+            .to_simple()
+            .unwrap()
+            .values()
+            .iter()
+            .zip(simple.values())
+        {
+            assert!((a - b).abs() < 1e-12);
+        }
+        assert!(Returns::from_prices(&prices, ReturnKind::Excess).is_none());
+    }
+
+    #[test]
+    fn test_excess_and_expect() {
+        let log = Returns::new(ReturnKind::Log, vec![0.01f64.ln_1p(), 0.02f64.ln_1p()]);
+        let excess = log.to_excess(&[0.001, 0.001]).unwrap();
+        assert_eq!(excess.kind(), ReturnKind::Excess);
+        assert!((excess.values()[0] - 0.009).abs() < 1e-12);
+        assert!((excess.values()[1] - 0.019).abs() < 1e-12);
+
+        // the risk-free part cannot be recovered
+        assert!(excess.to_simple().is_none());
+        assert!(excess.to_log().is_none());
+        assert!(log.to_excess(&[0.001]).is_none());
+
+        assert!(log.expect(ReturnKind::Log).is_some());
+        assert!(log.expect(ReturnKind::Simple).is_none());
+    }
+
+    #[test]
+    fn test_cagr() {
+        // doubling over two years of trading days
+        let equity: Vec<f64> = (0..=504).map(|i| 2f64.powf(i as f64 / 504.0)).collect();
+        assert!((cagr(&equity).unwrap() - (2f64.sqrt() - 1.0)).abs() < 1e-12);
+
+        let log = Returns::new(ReturnKind::Log, log_returns(&equity));
+        let from_returns = cagr_from_returns(&log).unwrap();
+        assert!((from_returns - (2f64.sqrt() - 1.0)).abs() < 1e-12);
+        assert!(
+            (cagr_from_returns(&log.to_simple().unwrap()).unwrap() - from_returns).abs() < 1e-12
+        );
+
+        // arithmetic annualizing would overstate a volatile series
+        let volatile = Returns::new(ReturnKind::Simple, vec![0.5, -0.5]);
+        assert!(cagr_from_returns(&volatile).unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_portfolio_aggregates_simple_returns() {
+        let a = Returns::new(ReturnKind::Log, vec![0.1f64.ln_1p(), (-0.2f64).ln_1p()]);
+        let b = Returns::new(ReturnKind::Simple, vec![0.0, 0.1]);
+        let port = portfolio_returns(&[a, b.clone()], &[0.5, 0.5]).unwrap();
+        assert_eq!(port.kind(), ReturnKind::Simple);
+        assert!((port.values()[0] - 0.05).abs() < 1e-12);
+        assert!((port.values()[1] + 0.05).abs() < 1e-12);
+
+        let short = Returns::new(ReturnKind::Simple, vec![0.0]);
+        assert!(portfolio_returns(&[b.clone(), short], &[0.5, 0.5]).is_none());
+        let excess = b.to_excess(&[0.0, 0.0]).unwrap();
+        assert!(portfolio_returns(&[b, excess.clone()], &[0.5, 0.5]).is_none());
+        let both = portfolio_returns(&[excess.clone(), excess], &[0.5, 0.5]).unwrap();
+        assert_eq!(both.kind(), ReturnKind::Excess);
     }
 }
 
+#[cfg(test)]
+mod benchmark_relative_tests {
+    use super::*;
+
+    const BENCH: [f64; 6] = [0.02, -0.01, 0.01, -0.02, 0.03, -0.01];
+
+    fn simple(values: &[f64]) -> Returns {
+        Returns::new(ReturnKind::Simple, values.to_vec())
+    }
+
+    fn bench() -> Returns {
+        simple(&BENCH)
+    }
+
+    #[test]
+    fn test_capture_ratios() {
+        let levered: Vec<f64> = BENCH.iter().map(|b| 2.0 * b).collect();
+        let up = up_capture(&simple(&levered), &bench()).unwrap();
+        let down = down_capture(&simple(&levered), &bench()).unwrap();
+        assert!(up > 1.9 && up < 2.1);
+        assert!(down > 1.9 && down < 2.1);
+
+        assert!((up_capture(&bench(), &bench()).unwrap() - 1.0).abs() < 1e-12);
+        // a strategy that sits out falling days
+        let defensive: Vec<f64> = BENCH.iter().map(|b| b.max(0.0)).collect();
+        assert_eq!(down_capture(&simple(&defensive), &bench()), Some(0.0));
+        assert!(up_capture(&simple(&[0.0; 3]), &simple(&[-0.01; 3])).is_none());
+    }
+
+    #[test]
+    fn test_batting_average() {
+        let rets = [0.03, -0.02, 0.01, -0.01, 0.02, 0.0];
+        assert_eq!(batting_average(&simple(&rets), &bench()), Some(3.0 / 6.0));
+        assert!(batting_average(&simple(&rets[1..]), &bench()).is_none());
+    }
+
+    #[test]
+    fn test_benchmark_metrics_reject_log_returns() {
+        let log = bench().to_log().unwrap();
+        assert!(up_capture(&log, &bench()).is_none());
+        assert!(batting_average(&bench(), &log).is_none());
+        assert!(active_return(&log, &log).is_none());
+    }
+
+    #[test]
+    fn test_up_down_correlation() {
+        let rets: Vec<f64> = BENCH
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if *b > 0.0 {
+                    0.5 * b
+                } else {
+                    -b + 0.001 * i as f64
+                }
+            })
+            .collect();
+        let (up, down) = up_down_correlation(&simple(&rets), &bench()).unwrap();
+        assert!((up - 1.0).abs() < 1e-10);
+        assert!(down < 0.0);
+    }
+
+    #[test]
+    fn test_active_return_decomposition() {
+        // beta 1.5 and a constant daily edge of 0.001
+        let rets: Vec<f64> = BENCH.iter().map(|b| 0.001 + 1.5 * b).collect();
+        let active = active_return(&simple(&rets), &bench()).unwrap();
+        let bench_mean = BENCH.iter().sum::<f64>() / BENCH.len() as f64;
+
+        assert!((active.beta_component - annualize_mean(0.5 * bench_mean)).abs() < 1e-12);
+        assert!((active.selection_component - annualize_mean(0.001)).abs() < 1e-12);
+        let te = tracking_error(&simple(&rets), &bench()).unwrap();
+        assert!((active.active_risk - te).abs() < 1e-12);
+    }
+}
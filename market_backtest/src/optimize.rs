@@ -0,0 +1,552 @@
+use crate::covariance::{self, CovarianceMatrix, Linkage};
+
+//
+// --------------------
+// Allocations
+// --------------------
+// Target weights (fractions of equity) in the covariance matrix's symbol
+// order. Expected returns passed in alongside must use the same order and the
+// same period as the covariances (daily, unless both are annualized).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Allocation {
+    pub symbols: Vec<String>,
+    pub weights: Vec<f64>,
+}
+
+impl Allocation {
+    pub fn weight(&self, symbol: &str) -> Option<f64> {
+        let i = self.symbols.iter().position(|s| s == symbol)?;
+        Some(self.weights[i])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.symbols
+            .iter()
+            .map(String::as_str)
+            .zip(self.weights.iter().copied())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Objective {
+    EqualWeight,
+    MinVariance,
+    /// Tangency portfolio for the given per-period risk-free rate
+    MaxSharpe {
+        risk_free: f64,
+    },
+    EqualRiskContribution,
+    HierarchicalRiskParity,
+}
+
+// Single entry point for strategies that pick the objective at runtime.
+// Expected returns are only read by `MaxSharpe`; constraints are ignored by
+// the risk-parity objectives, which are long-only and fully invested. Equal
+// weight gives `None` when 1/n breaks the weight bounds.
+pub fn optimize(
+    objective: Objective,
+    cov: &CovarianceMatrix,
+    expected: &[f64],
+    constraints: &Constraints,
+) -> Option<Allocation> {
+    let weights = match objective {
+        Objective::EqualWeight => constraints
+            .is_feasible(cov.len())
+            .then(|| vec![1.0 / cov.len() as f64; cov.len()]),
+        Objective::MinVariance => min_variance(cov, constraints),
+        Objective::MaxSharpe { risk_free } => max_sharpe(cov, expected, risk_free, constraints),
+        Objective::EqualRiskContribution => equal_risk_contribution(cov),
+        Objective::HierarchicalRiskParity => hierarchical_risk_parity(cov),
+    }?;
+    Some(Allocation {
+        symbols: cov.symbols.clone(),
+        weights,
+    })
+}
+
+//
+// --------------------
+// Constraints
+// --------------------
+// Weights always sum to 1. The default is long-only with no cap per asset.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Constraints {
+    /// Lower bound per asset; negative allows shorting
+    pub min_weight: f64,
+    /// Upper bound per asset
+    pub max_weight: f64,
+    /// Cap on gross exposure (sum of absolute weights); at least 1
+    pub leverage: f64,
+}
+
+impl Default for Constraints {
+    fn default() -> Self {
+        Self {
+            min_weight: 0.0,
+            max_weight: 1.0,
+            leverage: 1.0,
+        }
+    }
+}
+
+impl Constraints {
+    fn is_feasible(&self, assets: usize) -> bool {
+        let n = assets as f64;
+        assets > 0
+            && self.min_weight <= self.max_weight
+            && n * self.min_weight <= 1.0
+            && n * self.max_weight >= 1.0
+            && self.leverage >= 1.0
+    }
+
+    // Closest feasible weights to `v` (Euclidean): each weight is `v` shifted
+    // by a common amount, shrunk toward zero while gross exposure is over the
+    // cap, then clipped to the bounds; both amounts are found by bisection
+    fn project(&self, v: &[f64]) -> Vec<f64> {
+        let weights = |tau: f64, rho: f64| -> Vec<f64> {
+            v.iter()
+                .map(|x| {
+                    let shifted = x - tau;
+                    let shrunk = shifted.signum() * (shifted.abs() - rho).max(0.0);
+                    shrunk.clamp(self.min_weight, self.max_weight)
+                })
+                .collect()
+        };
+        let fully_invested = |rho: f64| -> Vec<f64> {
+            let spread = self.min_weight.abs().max(self.max_weight.abs()) + rho + 1.0;
+            let mut lo = v.iter().copied().fold(f64::INFINITY, f64::min) - spread;
+            let mut hi = v.iter().copied().fold(f64::NEG_INFINITY, f64::max) + spread;
+            for _ in 0..200 {
+                let mid = 0.5 * (lo + hi);
+                if weights(mid, rho).iter().sum::<f64>() > 1.0 {
+                    lo = mid;
+                } else {
+                    hi = mid;
+                }
+            }
+            weights(0.5 * (lo + hi), rho)
+        };
+        let gross = |w: &[f64]| w.iter().map(|x| x.abs()).sum::<f64>();
+
+        let unshrunk = fully_invested(0.0);
+        if gross(&unshrunk) <= self.leverage + 1e-12 {
+            return unshrunk;
+        }
+        let mut hi = 1.0;
+        while gross(&fully_invested(hi)) > self.leverage + 1e-12 {
+            hi *= 2.0;
+        }
+        let mut lo = 0.0;
+        for _ in 0..100 {
+            let mid = 0.5 * (lo + hi);
+            if gross(&fully_invested(mid)) > self.leverage {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        fully_invested(hi)
+    }
+}
+
+fn multiply(matrix: &[Vec<f64>], v: &[f64]) -> Vec<f64> {
+    matrix
+        .iter()
+        .map(|row| row.iter().zip(v).map(|(a, b)| a * b).sum())
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn max_abs_diff(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f64::max)
+}
+
+//
+// --------------------
+// Mean-Variance
+// --------------------
+// Lowest-variance portfolio within the constraints (accelerated projected
+// gradient descent)
+pub fn min_variance(cov: &CovarianceMatrix, constraints: &Constraints) -> Option<Vec<f64>> {
+    let n = cov.len();
+    if !constraints.is_feasible(n) {
+        return None;
+    }
+    // the largest absolute row sum bounds the largest eigenvalue
+    let bound = cov
+        .values
+        .iter()
+        .map(|row| row.iter().map(|x| x.abs()).sum::<f64>())
+        .fold(0.0, f64::max);
+    if bound == 0.0 {
+        return Some(constraints.project(&vec![1.0 / n as f64; n]));
+    }
+    let step = 1.0 / (2.0 * bound);
+
+    let mut x = constraints.project(&vec![1.0 / n as f64; n]);
+    let mut y = x.clone();
+    let mut t = 1.0f64;
+    for _ in 0..50_000 {
+        let gradient = multiply(&cov.values, &y);
+        let moved: Vec<f64> = y
+            .iter()
+            .zip(&gradient)
+            .map(|(w, g)| w - step * 2.0 * g)
+            .collect();
+        let next = constraints.project(&moved);
+        let t_next = (1.0 + (1.0 + 4.0 * t * t).sqrt()) / 2.0;
+        let momentum = (t - 1.0) / t_next;
+        y = next
+            .iter()
+            .zip(&x)
+            .map(|(a, b)| a + momentum * (a - b))
+            .collect();
+        let done = max_abs_diff(&next, &x) < 1e-12;
+        x = next;
+        t = t_next;
+        if done {
+            break;
+        }
+    }
+    Some(x)
+}
+
+// Highest (expected - risk_free) / volatility within the constraints.
+// None if no asset is expected to beat the risk-free rate.
+pub fn max_sharpe(
+    cov: &CovarianceMatrix,
+    expected: &[f64],
+    risk_free: f64,
+    constraints: &Constraints,
+) -> Option<Vec<f64>> {
+    let n = cov.len();
+    if !constraints.is_feasible(n) || expected.len() != n {
+        return None;
+    }
+    let excess: Vec<f64> = expected.iter().map(|r| r - risk_free).collect();
+    if excess.iter().all(|e| *e <= 0.0) {
+        return None;
+    }
+    let sharpe = |w: &[f64]| {
+        let var = dot(w, &multiply(&cov.values, w));
+        if var > 0.0 {
+            dot(&excess, w) / var.sqrt()
+        } else {
+            f64::NEG_INFINITY
+        }
+    };
+
+    // Sharpe is pseudo-concave where the excess return is positive, so
+    // gradient ascent finds the global maximum; the step adapts by
+    // backtracking
+    let mut w = constraints.project(&vec![1.0 / n as f64; n]);
+    let mut current = sharpe(&w);
+    let mut step = 1.0;
+    for _ in 0..20_000 {
+        let sigma_w = multiply(&cov.values, &w);
+        let var = dot(&w, &sigma_w);
+        if var <= 0.0 {
+            return None;
+        }
+        let vol = var.sqrt();
+        let mean = dot(&excess, &w);
+        let gradient: Vec<f64> = excess
+            .iter()
+            .zip(&sigma_w)
+            .map(|(e, s)| e / vol - mean * s / (var * vol))
+            .collect();
+
+        let mut improved = false;
+        while step > 1e-14 {
+            let moved: Vec<f64> = w.iter().zip(&gradient).map(|(x, g)| x + step * g).collect();
+            let candidate = constraints.project(&moved);
+            let value = sharpe(&candidate);
+            if value > current {
+                let change = max_abs_diff(&candidate, &w);
+                improved = change > 1e-12 && value - current > 1e-15;
+                w = candidate;
+                current = value;
+                step *= 2.0;
+                break;
+            }
+            step /= 2.0;
+        }
+        if !improved {
+            break;
+        }
+    }
+    Some(w)
+}
+
+//
+// --------------------
+// Risk Parity
+// --------------------
+// Each asset's share of portfolio variance, w_i (Σw)_i / w'Σw; sums to 1
+pub fn risk_contributions(cov: &CovarianceMatrix, weights: &[f64]) -> Option<Vec<f64>> {
+    let var = cov.portfolio_variance(weights)?;
+    if var <= 0.0 {
+        return None;
+    }
+    let marginal = multiply(&cov.values, weights);
+    Some(
+        weights
+            .iter()
+            .zip(marginal)
+            .map(|(w, m)| w * m / var)
+            .collect(),
+    )
+}
+
+// Long-only weights whose risk contributions match `budgets` (normalized to
+// sum to 1), by cyclical coordinate descent on Spinu's convex formulation
+pub fn risk_budget(cov: &CovarianceMatrix, budgets: &[f64]) -> Option<Vec<f64>> {
+    let n = cov.len();
+    let total: f64 = budgets.iter().sum();
+    if n == 0
+        || budgets.len() != n
+        || budgets.iter().any(|b| *b <= 0.0)
+        || (0..n).any(|i| cov.values[i][i] <= 0.0)
+    {
+        return None;
+    }
+    let b: Vec<f64> = budgets.iter().map(|x| x / total).collect();
+    let sigma = &cov.values;
+
+    let mut y: Vec<f64> = (0..n).map(|i| 1.0 / sigma[i][i].sqrt()).collect();
+    for _ in 0..10_000 {
+        let previous = y.clone();
+        for i in 0..n {
+            let others: f64 = (0..n).filter(|&j| j != i).map(|j| sigma[i][j] * y[j]).sum();
+            y[i] = (-others + (others * others + 4.0 * sigma[i][i] * b[i]).sqrt())
+                / (2.0 * sigma[i][i]);
+        }
+        let scale: f64 = y.iter().sum();
+        if max_abs_diff(&y, &previous) / scale < 1e-13 {
+            break;
+        }
+    }
+    let sum: f64 = y.iter().sum();
+    Some(y.iter().map(|x| x / sum).collect())
+}
+
+pub fn equal_risk_contribution(cov: &CovarianceMatrix) -> Option<Vec<f64>> {
+    risk_budget(cov, &vec![1.0; cov.len()])
+}
+
+// López de Prado's hierarchical risk parity: order assets by single-linkage
+// clustering of their correlations, then split the ordered list in halves
+// recursively, dividing weight between the halves in inverse proportion to
+// their (inverse-variance weighted) variance. Needs no matrix inversion.
+pub fn hierarchical_risk_parity(cov: &CovarianceMatrix) -> Option<Vec<f64>> {
+    let n = cov.len();
+    if n == 0 || (0..n).any(|i| cov.values[i][i] <= 0.0) {
+        return None;
+    }
+    let tree = covariance::cluster(&cov.correlation(), Linkage::Single)?;
+
+    let cluster_variance = |items: &[usize]| {
+        let inverse: Vec<f64> = items.iter().map(|&i| 1.0 / cov.values[i][i]).collect();
+        let total: f64 = inverse.iter().sum();
+        let w: Vec<f64> = inverse.iter().map(|x| x / total).collect();
+        let mut var = 0.0;
+        for (a, &i) in items.iter().enumerate() {
+            for (b, &j) in items.iter().enumerate() {
+                var += w[a] * w[b] * cov.values[i][j];
+            }
+        }
+        var
+    };
+
+    let mut weights = vec![1.0; n];
+    let mut pending = vec![tree.order()];
+    while let Some(items) = pending.pop() {
+        if items.len() < 2 {
+            continue;
+        }
+        let (left, right) = items.split_at(items.len() / 2);
+        let (v_left, v_right) = (cluster_variance(left), cluster_variance(right));
+        let alpha = 1.0 - v_left / (v_left + v_right);
+        left.iter().for_each(|&i| weights[i] *= alpha);
+        right.iter().for_each(|&i| weights[i] *= 1.0 - alpha);
+        pending.push(left.to_vec());
+        pending.push(right.to_vec());
+    }
+    Some(weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(values: Vec<Vec<f64>>) -> CovarianceMatrix {
+        CovarianceMatrix {
+            symbols: (0..values.len()).map(|i| format!("S{}", i)).collect(),
+            values,
+        }
+    }
+
+    // Uncorrelated assets with daily volatilities of 1% and 2%
+    fn diagonal() -> CovarianceMatrix {
+        matrix(vec![vec![1e-4, 0.0], vec![0.0, 4e-4]])
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64], tol: f64) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < tol, "{:?} vs {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_min_variance() {
+        let cov = diagonal();
+        let w = min_variance(&cov, &Constraints::default()).unwrap();
+        assert_close(&w, &[0.8, 0.2], 1e-6);
+
+        let capped = Constraints {
+            max_weight: 0.7,
+            ..Default::default()
+        };
+        assert_close(&min_variance(&cov, &capped).unwrap(), &[0.7, 0.3], 1e-6);
+
+        // two assets cannot each be held at 20% or less
+        let infeasible = Constraints {
+            max_weight: 0.2,
+            ..Default::default()
+        };
+        assert!(min_variance(&cov, &infeasible).is_none());
+    }
+
+    #[test]
+    fn test_max_sharpe_matches_tangency_portfolio() {
+        // unconstrained tangency weights are proportional to Σ⁻¹(μ - rf)
+        let cov = diagonal();
+        let w = max_sharpe(&cov, &[0.001, 0.002], 0.0, &Constraints::default()).unwrap();
+        assert_close(&w, &[2.0 / 3.0, 1.0 / 3.0], 1e-5);
+
+        assert!(max_sharpe(&cov, &[-0.001, 0.0], 0.0, &Constraints::default()).is_none());
+        assert!(max_sharpe(&cov, &[0.001], 0.0, &Constraints::default()).is_none());
+    }
+
+    #[test]
+    fn test_shorting_and_leverage() {
+        // the second asset is expected to lose money
+        let cov = matrix(vec![
+            vec![1e-4, 2e-5, 0.0],
+            vec![2e-5, 1e-4, 0.0],
+            vec![0.0, 0.0, 1e-4],
+        ]);
+        let expected = [0.001, -0.0005, 0.0008];
+        let long_only = max_sharpe(&cov, &expected, 0.0, &Constraints::default()).unwrap();
+        let long_short = Constraints {
+            min_weight: -0.5,
+            max_weight: 1.0,
+            leverage: 1.5,
+        };
+        let w = max_sharpe(&cov, &expected, 0.0, &long_short).unwrap();
+
+        assert!(long_only[1].abs() < 1e-9);
+        assert!(w[1] < 0.0);
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(w.iter().map(|x| x.abs()).sum::<f64>() <= 1.5 + 1e-9);
+        let sharpe = |w: &[f64]| dot(&expected, w) / cov.portfolio_variance(w).unwrap().sqrt();
+        assert!(sharpe(&w) > sharpe(&long_only));
+    }
+
+    #[test]
+    fn test_projection_respects_every_constraint() {
+        let c = Constraints {
+            min_weight: -1.0,
+            max_weight: 1.0,
+            leverage: 1.5,
+        };
+        let w = c.project(&[2.0, -1.5, 0.5]);
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(w.iter().map(|x| x.abs()).sum::<f64>() <= 1.5 + 1e-9);
+        assert!(w.iter().all(|x| (-1.0..=1.0).contains(x)));
+        // already feasible points stay put
+        assert_close(&c.project(&[0.6, 0.4, 0.0]), &[0.6, 0.4, 0.0], 1e-9);
+    }
+
+    #[test]
+    fn test_equal_risk_contribution() {
+        // uncorrelated: weights proportional to 1 / volatility
+        let w = equal_risk_contribution(&diagonal()).unwrap();
+        assert_close(&w, &[2.0 / 3.0, 1.0 / 3.0], 1e-9);
+
+        let cov = matrix(vec![
+            vec![1e-4, 5e-5, 1e-5],
+            vec![5e-5, 4e-4, -2e-5],
+            vec![1e-5, -2e-5, 9e-4],
+        ]);
+        let w = equal_risk_contribution(&cov).unwrap();
+        let rc = risk_contributions(&cov, &w).unwrap();
+        assert_close(&rc, &[1.0 / 3.0; 3], 1e-9);
+
+        let budgeted = risk_budget(&cov, &[2.0, 1.0, 1.0]).unwrap();
+        let rc = risk_contributions(&cov, &budgeted).unwrap();
+        assert_close(&rc, &[0.5, 0.25, 0.25], 1e-9);
+    }
+
+    #[test]
+    fn test_hierarchical_risk_parity() {
+        // equal, uncorrelated assets get equal weight
+        let same = matrix(vec![vec![1e-4, 0.0], vec![0.0, 1e-4]]);
+        assert_close(
+            &hierarchical_risk_parity(&same).unwrap(),
+            &[0.5, 0.5],
+            1e-12,
+        );
+
+        let cov = matrix(vec![
+            vec![1e-4, 8e-5, 0.0, 0.0],
+            vec![8e-5, 1e-4, 0.0, 0.0],
+            vec![0.0, 0.0, 4e-4, 1e-4],
+            vec![0.0, 0.0, 1e-4, 9e-4],
+        ]);
+        let w = hierarchical_risk_parity(&cov).unwrap();
+        assert!((w.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(w.iter().all(|x| *x > 0.0));
+        // the low-volatility pair carries more weight
+        assert!(w[0] + w[1] > w[2] + w[3]);
+        assert!(w[2] > w[3]);
+    }
+
+    #[test]
+    fn test_optimize_dispatch() {
+        let cov = diagonal();
+        let c = Constraints::default();
+        let equal = optimize(Objective::EqualWeight, &cov, &[], &c).unwrap();
+        assert_eq!(equal.weights, vec![0.5, 0.5]);
+        assert_eq!(equal.weight("S1"), Some(0.5));
+        // two assets cannot both be at most 40%
+        let capped = Constraints {
+            max_weight: 0.4,
+            ..c
+        };
+        assert!(optimize(Objective::EqualWeight, &cov, &[], &capped).is_none());
+
+        let sharpe = optimize(
+            Objective::MaxSharpe { risk_free: 0.0 },
+            &cov,
+            &[0.001, 0.002],
+            &c,
+        )
+        .unwrap();
+        assert_eq!(sharpe.symbols, cov.symbols);
+        for objective in [
+            Objective::MinVariance,
+            Objective::EqualRiskContribution,
+            Objective::HierarchicalRiskParity,
+        ] {
+            let a = optimize(objective, &cov, &[], &c).unwrap();
+            assert!((a.iter().map(|(_, w)| w).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use crate::data::FactorData;
+use chrono::NaiveDate;
+use statrs::distribution::{ContinuousCDF, StudentsT};
+use std::fmt;
+
+//
+// --------------------
+// Regression Output
+// --------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coefficient {
+    pub estimate: f64,
+    pub std_error: f64,
+    pub t_stat: f64,
+    /// Two-sided p-value for the coefficient being zero
+    pub p_value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    /// Intercept, in the units of the dependent series (daily for daily data)
+    pub alpha: Coefficient,
+    /// One slope per regressor, in the order they were passed
+    pub betas: Vec<Coefficient>,
+    pub names: Vec<String>,
+    pub r_squared: f64,
+    pub adj_r_squared: f64,
+    /// Standard deviation of the residuals
+    pub residual_std: f64,
+    pub observations: usize,
+    pub std_errors: StdErrors,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StdErrors {
+    /// Homoskedastic, serially uncorrelated residuals
+    #[default]
+    Classic,
+    /// Heteroskedasticity and autocorrelation consistent, Bartlett kernel
+    NeweyWest { lags: usize },
+}
+
+impl StdErrors {
+    /// Newey–West with the usual rule-of-thumb lag length 4·(n/100)^(2/9)
+    pub fn newey_west(observations: usize) -> Self {
+        let lags = (4.0 * (observations as f64 / 100.0).powf(2.0 / 9.0)).floor() as usize;
+        StdErrors::NeweyWest { lags }
+    }
+}
+
+impl fmt::Display for StdErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StdErrors::Classic => write!(f, "classic"),
+            StdErrors::NeweyWest { lags } => write!(f, "Newey–West, {} lags", lags),
+        }
+    }
+}
+
+//
+// --------------------
+// Ordinary Least Squares
+// --------------------
+// Regresses `y` on an intercept plus each series in `regressors`. Returns None
+// if the lengths differ, there are no more observations than parameters, or
+// the regressors are collinear.
+pub fn ols(
+    y: &[f64],
+    regressors: &[&[f64]],
+    names: &[&str],
+    std_errors: StdErrors,
+) -> Option<Regression> {
+    let n = y.len();
+    let k = regressors.len() + 1;
+    if n <= k || names.len() != regressors.len() || regressors.iter().any(|x| x.len() != n) {
+        return None;
+    }
+
+    // row t of the design matrix: [1, x1_t, x2_t, ...]
+    let row = |t: usize| -> Vec<f64> {
+        std::iter::once(1.0)
+            .chain(regressors.iter().map(|x| x[t]))
+            .collect()
+    };
+
+    let mut xtx = vec![vec![0.0; k]; k];
+    let mut xty = vec![0.0; k];
+    for (t, yt) in y.iter().enumerate() {
+        let x = row(t);
+        for i in 0..k {
+            xty[i] += x[i] * yt;
+            for j in 0..k {
+                xtx[i][j] += x[i] * x[j];
+            }
+        }
+    }
+    let xtx_inv = invert(&xtx)?;
+    let coefs: Vec<f64> = (0..k)
+        .map(|i| (0..k).map(|j| xtx_inv[i][j] * xty[j]).sum())
+        .collect();
+
+    let residuals: Vec<f64> = (0..n)
+        .map(|t| y[t] - row(t).iter().zip(&coefs).map(|(x, b)| x * b).sum::<f64>())
+        .collect();
+    let sse: f64 = residuals.iter().map(|e| e * e).sum();
+    let y_mean = y.iter().sum::<f64>() / n as f64;
+    let sst: f64 = y.iter().map(|v| (v - y_mean).powi(2)).sum();
+    let dof = (n - k) as f64;
+
+    let cov = match std_errors {
+        StdErrors::Classic => {
+            let s2 = sse / dof;
+            xtx_inv
+                .iter()
+                .map(|r| r.iter().map(|v| v * s2).collect())
+                .collect()
+        }
+        StdErrors::NeweyWest { lags } => {
+            let meat = newey_west_meat(&residuals, &row, k, lags);
+            sandwich(&xtx_inv, &meat)
+        }
+    };
+
+    let t_dist = StudentsT::new(0.0, 1.0, dof).ok()?;
+    let coefficient = |i: usize| {
+        let std_error = cov[i][i].max(0.0).sqrt();
+        let t_stat = coefs[i] / std_error;
+        Coefficient {
+            estimate: coefs[i],
+            std_error,
+            t_stat,
+            p_value: 2.0 * (1.0 - t_dist.cdf(t_stat.abs())),
+        }
+    };
+
+    let r_squared = if sst > 0.0 { 1.0 - sse / sst } else { 0.0 };
+    Some(Regression {
+        alpha: coefficient(0),
+        betas: (1..k).map(coefficient).collect(),
+        names: names.iter().map(|s| s.to_string()).collect(),
+        r_squared,
+        adj_r_squared: 1.0 - (1.0 - r_squared) * (n - 1) as f64 / dof,
+        residual_std: (sse / dof).sqrt(),
+        observations: n,
+        std_errors,
+    })
+}
+
+// S = Σ e_t² x_t x_t' + Σ_l w_l Σ_t e_t e_{t-l} (x_t x_{t-l}' + x_{t-l} x_t')
+fn newey_west_meat(
+    residuals: &[f64],
+    row: &dyn Fn(usize) -> Vec<f64>,
+    k: usize,
+    lags: usize,
+) -> Vec<Vec<f64>> {
+    let n = residuals.len();
+    let scores: Vec<Vec<f64>> = (0..n)
+        .map(|t| row(t).iter().map(|x| x * residuals[t]).collect())
+        .collect();
+
+    let mut meat = vec![vec![0.0; k]; k];
+    for lag in 0..=lags.min(n - 1) {
+        let weight = if lag == 0 {
+            1.0
+        } else {
+            1.0 - lag as f64 / (lags + 1) as f64
+        };
+        for t in lag..n {
+            let (a, b) = (&scores[t], &scores[t - lag]);
+            for i in 0..k {
+                for j in 0..k {
+                    meat[i][j] += if lag == 0 {
+                        a[i] * b[j]
+                    } else {
+                        weight * (a[i] * b[j] + b[i] * a[j])
+                    };
+                }
+            }
+        }
+    }
+    meat
+}
+
+// (X'X)^-1 S (X'X)^-1
+fn sandwich(bread: &[Vec<f64>], meat: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let product = |a: &[Vec<f64>], b: &[Vec<f64>]| -> Vec<Vec<f64>> {
+        (0..a.len())
+            .map(|i| {
+                (0..b[0].len())
+                    .map(|j| (0..b.len()).map(|m| a[i][m] * b[m][j]).sum())
+                    .collect()
+            })
+            .collect()
+    };
+    product(&product(bread, meat), bread)
+}
+
+// Gauss–Jordan elimination with partial pivoting
+fn invert(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut a: Vec<Vec<f64>> = matrix.to_vec();
+    let mut inv: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect();
+    let scale = matrix.iter().flatten().fold(0.0_f64, |m, v| m.max(v.abs()));
+
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() <= 1e-12 * scale.max(1.0) {
+            return None;
+        }
+        a.swap(col, pivot);
+        inv.swap(col, pivot);
+
+        let p = a[col][col];
+        for j in 0..n {
+            a[col][j] /= p;
+            inv[col][j] /= p;
+        }
+        for i in 0..n {
+            if i != col {
+                let f = a[i][col];
+                for j in 0..n {
+                    a[i][j] -= f * a[col][j];
+                    inv[i][j] -= f * inv[col][j];
+                }
+            }
+        }
+    }
+    Some(inv)
+}
+
+//
+// --------------------
+// CAPM
+// --------------------
+// Excess asset returns on excess market returns; the intercept is Jensen's
+// alpha (same point estimate as `metrics::alpha`) with its standard error.
+pub fn capm(
+    asset_rets: &[f64],
+    market_rets: &[f64],
+    rf_rets: &[f64],
+    std_errors: StdErrors,
+) -> Option<Regression> {
+    if asset_rets.len() != market_rets.len() || asset_rets.len() != rf_rets.len() {
+        return None;
+    }
+    let excess_asset: Vec<f64> = asset_rets
+        .iter()
+        .zip(rf_rets)
+        .map(|(a, rf)| a - rf)
+        .collect();
+    let excess_market: Vec<f64> = market_rets
+        .iter()
+        .zip(rf_rets)
+        .map(|(m, rf)| m - rf)
+        .collect();
+    ols(&excess_asset, &[&excess_market], &["Market"], std_errors)
+}
+
+//
+// --------------------
+// Multi-factor Models
+// --------------------
+// Regresses returns on every factor in `factors`, matching observations by
+// date. `dates[i]` is the date of `returns[i]`; dates missing from the factor
+// file are dropped. Returns are taken in excess of the file's RF column when
+// it has one (factor files such as Fama–French already hold excess market
+// returns).
+pub fn factor_model(
+    dates: &[NaiveDate],
+    returns: &[f64],
+    factors: &FactorData,
+    std_errors: StdErrors,
+) -> Option<Regression> {
+    if dates.len() != returns.len() {
+        return None;
+    }
+    let index = factors.index();
+    let rows: Vec<(usize, f64)> = dates
+        .iter()
+        .zip(returns)
+        .filter_map(|(d, r)| index.get(d).map(|&i| (i, *r)))
+        .collect();
+
+    let y: Vec<f64> = rows
+        .iter()
+        .map(|&(i, r)| r - factors.risk_free.as_ref().map_or(0.0, |rf| rf[i]))
+        .collect();
+    let columns: Vec<Vec<f64>> = factors
+        .factors
+        .iter()
+        .map(|f| rows.iter().map(|&(i, _)| f[i]).collect())
+        .collect();
+    let regressors: Vec<&[f64]> = columns.iter().map(|c| c.as_slice()).collect();
+    let names: Vec<&str> = factors.names.iter().map(|s| s.as_str()).collect();
+    ols(&y, &regressors, &names, std_errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data;
+
+    fn noise(n: usize, seed: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| ((i * 7919 + seed * 104_729) % 1000) as f64 / 1000.0 - 0.5)
+            .collect()
+    }
+
+    #[test]
+    fn test_ols_recovers_exact_fit() {
+        let x1 = noise(50, 1);
+        let x2 = noise(50, 2);
+        let y: Vec<f64> = x1
+            .iter()
+            .zip(&x2)
+            .map(|(a, b)| 0.5 + 2.0 * a - 1.0 * b)
+            .collect();
+        let fit = ols(&y, &[&x1, &x2], &["a", "b"], StdErrors::Classic).unwrap();
+        assert!((fit.alpha.estimate - 0.5).abs() < 1e-10);
+        assert!((fit.betas[0].estimate - 2.0).abs() < 1e-10);
+        assert!((fit.betas[1].estimate + 1.0).abs() < 1e-10);
+        assert!((fit.r_squared - 1.0).abs() < 1e-10);
+        assert_eq!(fit.names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_ols_classic_standard_errors() {
+        // y = [1, 3, 2, 5], x = [0, 1, 2, 3]: slope 1.1, intercept 1.1,
+        // SSE 2.7, s² = 1.35, Var(slope) = s² / Σ(x - x̄)² = 1.35 / 5
+        let y = [1.0, 3.0, 2.0, 5.0];
+        let x = [0.0, 1.0, 2.0, 3.0];
+        let fit = ols(&y, &[&x], &["x"], StdErrors::Classic).unwrap();
+        assert!((fit.betas[0].estimate - 1.1).abs() < 1e-12);
+        assert!((fit.alpha.estimate - 1.1).abs() < 1e-12);
+        assert!((fit.betas[0].std_error - (1.35f64 / 5.0).sqrt()).abs() < 1e-12);
+        // Var(alpha) = s² (1/n + x̄² / Sxx)
+        let se_alpha = (1.35f64 * (0.25 + 2.25 / 5.0)).sqrt();
+        assert!((fit.alpha.std_error - se_alpha).abs() < 1e-12);
+        assert!((fit.r_squared - (1.0 - 2.7 / 8.75)).abs() < 1e-12);
+        assert!(fit.betas[0].p_value > 0.0 && fit.betas[0].p_value < 1.0);
+    }
+
+    #[test]
+    fn test_newey_west() {
+        let x = noise(200, 3);
+        let e = noise(200, 4);
+        let y: Vec<f64> = x.iter().zip(&e).map(|(a, b)| 0.1 + 0.8 * a + b).collect();
+
+        let classic = ols(&y, &[&x], &["x"], StdErrors::Classic).unwrap();
+        let hac = ols(&y, &[&x], &["x"], StdErrors::NeweyWest { lags: 5 }).unwrap();
+        // same point estimates, different uncertainty
+        assert_eq!(classic.betas[0].estimate, hac.betas[0].estimate);
+        assert!(hac.betas[0].std_error > 0.0);
+        assert_ne!(classic.betas[0].std_error, hac.betas[0].std_error);
+
+        assert_eq!(StdErrors::newey_west(100), StdErrors::NeweyWest { lags: 4 });
+    }
+
+    #[test]
+    fn test_ols_rejects_bad_input() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+        assert!(ols(&[1.0, 2.0], &[&x], &["x"], StdErrors::Classic).is_none());
+        // collinear regressors
+        let y = [1.0, 3.0, 2.0, 5.0];
+        let x2: Vec<f64> = x.iter().map(|v| v * 2.0).collect();
+        assert!(ols(&y, &[&x, &x2], &["x", "2x"], StdErrors::Classic).is_none());
+    }
+
+    #[test]
+    fn test_capm_matches_metrics_alpha() {
+        let market = noise(60, 5);
+        let asset: Vec<f64> = market
+            .iter()
+            .zip(noise(60, 6))
+            .map(|(m, e)| 0.001 + 1.2 * m + 0.1 * e)
+            .collect();
+        let rf = vec![0.0001; 60];
+        let fit = capm(&asset, &market, &rf, StdErrors::Classic).unwrap();
+        let alpha = crate::metrics::alpha(&asset, &market, &rf).unwrap();
+        assert!((fit.alpha.estimate - alpha).abs() < 1e-12);
+        let beta = crate::metrics::beta(&asset, &market).unwrap();
+        assert!((fit.betas[0].estimate - beta).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_factor_model_from_csv() {
+        let csv = "Date,Mkt-RF,SMB,RF\n\
+                   20250102,1.0,0.5,0.01\n\
+                   20250103,-0.5,0.2,0.01\n\
+                   20250106,0.3,-0.4,0.01\n\
+                   20250107,0.8,0.1,0.01\n\
+                   20250108,-0.2,0.3,0.01\n\
+                   20250109,,0.3,0.01\n";
+        let factors = data::load_factors_from_reader(csv.as_bytes(), true).unwrap();
+        assert_eq!(factors.names, vec!["Mkt-RF", "SMB"]);
+        assert_eq!(factors.dates.len(), 5);
+        assert!((factors.risk_free.as_ref().unwrap()[0] - 0.0001).abs() < 1e-15);
+
+        // returns built from the factors, plus one date the file lacks
+        let mut dates = factors.dates.clone();
+        let mut returns: Vec<f64> = (0..5)
+            .map(|i| 0.0001 + 0.0002 + 1.5 * factors.factors[0][i] - 0.5 * factors.factors[1][i])
+            .collect();
+        dates.push(NaiveDate::from_ymd_opt(2025, 1, 10).unwrap());
+        returns.push(0.5);
+
+        let fit = factor_model(&dates, &returns, &factors, StdErrors::Classic).unwrap();
+        assert_eq!(fit.observations, 5);
+        assert!((fit.alpha.estimate - 0.0002).abs() < 1e-12);
+        assert!((fit.betas[0].estimate - 1.5).abs() < 1e-9);
+        assert!((fit.betas[1].estimate + 0.5).abs() < 1e-9);
+    }
+}
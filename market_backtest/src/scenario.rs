@@ -0,0 +1,281 @@
+use crate::backtest::Portfolio;
+use crate::metrics;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+
+// Fewer overlapping days than this and a symbol's own beta is not trusted
+pub const MIN_HISTORY: usize = 20;
+
+//
+// --------------------
+// Scenarios
+// --------------------
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scenario {
+    /// Replays what happened between two dates: each symbol's own compounded
+    /// return where it has the full window, otherwise beta times the
+    /// benchmark's
+    Historical {
+        name: String,
+        start: NaiveDate,
+        end: NaiveDate,
+    },
+    /// The benchmark moves by `shock` (e.g. -0.20); symbols follow by beta
+    Market { name: String, shock: f64 },
+    /// The reference yield moves by `bp` basis points; symbols follow by their
+    /// sensitivity to past yield changes
+    Rates { name: String, bp: f64 },
+}
+
+impl Scenario {
+    pub fn name(&self) -> &str {
+        match self {
+            Scenario::Historical { name, .. }
+            | Scenario::Market { name, .. }
+            | Scenario::Rates { name, .. } => name,
+        }
+    }
+}
+
+//
+// --------------------
+// Market Data
+// --------------------
+// Daily simple returns keyed by date, so series from different files line up
+// on the days they share
+#[derive(Debug, Clone, Default)]
+pub struct ScenarioData {
+    pub benchmark: BTreeMap<NaiveDate, f64>,
+    pub symbols: HashMap<String, BTreeMap<NaiveDate, f64>>,
+    /// Daily change of the reference yield, as a decimal (0.0001 = 1bp)
+    pub yield_changes: BTreeMap<NaiveDate, f64>,
+    /// Assumed betas for symbols without enough history (1.0 otherwise)
+    pub proxy_betas: HashMap<String, f64>,
+}
+
+// Pairs `dates[i]` with `values[i]`
+pub fn dated(dates: &[NaiveDate], values: &[f64]) -> BTreeMap<NaiveDate, f64> {
+    dates.iter().copied().zip(values.iter().copied()).collect()
+}
+
+// Day-over-day changes of yield levels given in any date order
+pub fn yield_changes(levels: &[(NaiveDate, f64)]) -> BTreeMap<NaiveDate, f64> {
+    let sorted: BTreeMap<NaiveDate, f64> = levels.iter().copied().collect();
+    sorted
+        .iter()
+        .zip(sorted.iter().skip(1))
+        .map(|((_, prev), (date, level))| (*date, level - prev))
+        .collect()
+}
+
+// Values of both series on the dates they share
+fn aligned(a: &BTreeMap<NaiveDate, f64>, b: &BTreeMap<NaiveDate, f64>) -> (Vec<f64>, Vec<f64>) {
+    a.iter()
+        .filter_map(|(d, x)| b.get(d).map(|y| (*x, *y)))
+        .unzip()
+}
+
+impl ScenarioData {
+    /// Beta to the benchmark from the symbol's history, or its proxy
+    pub fn beta(&self, symbol: &str) -> f64 {
+        self.symbols
+            .get(symbol)
+            .map(|history| aligned(history, &self.benchmark))
+            .filter(|(s, _)| s.len() >= MIN_HISTORY)
+            .and_then(|(s, b)| metrics::beta(&s, &b))
+            .or_else(|| self.proxy_betas.get(symbol).copied())
+            .unwrap_or(1.0)
+    }
+
+    /// Return per unit change in the reference yield (e.g. -7.0 for a bond
+    /// fund of duration ~7); 0.0 without enough overlapping history
+    pub fn rate_sensitivity(&self, symbol: &str) -> f64 {
+        self.symbols
+            .get(symbol)
+            .map(|history| aligned(history, &self.yield_changes))
+            .filter(|(s, _)| s.len() >= MIN_HISTORY)
+            .and_then(|(s, dy)| metrics::beta(&s, &dy))
+            .unwrap_or(0.0)
+    }
+
+    fn window_return(series: &BTreeMap<NaiveDate, f64>, start: NaiveDate, end: NaiveDate) -> f64 {
+        series
+            .range(start..=end)
+            .map(|(_, r)| 1.0 + r)
+            .product::<f64>()
+            - 1.0
+    }
+
+    fn covers(series: &BTreeMap<NaiveDate, f64>, start: NaiveDate, end: NaiveDate) -> bool {
+        let first = series.keys().next();
+        let last = series.keys().next_back();
+        matches!((first, last), (Some(f), Some(l)) if *f <= start && *l >= end)
+    }
+
+    /// Simple return of `symbol` under `scenario`
+    pub fn shocked_return(&self, symbol: &str, scenario: &Scenario) -> f64 {
+        match scenario {
+            Scenario::Historical { start, end, .. } => match self.symbols.get(symbol) {
+                Some(history) if Self::covers(history, *start, *end) => {
+                    Self::window_return(history, *start, *end)
+                }
+                _ => self.beta(symbol) * Self::window_return(&self.benchmark, *start, *end),
+            },
+            Scenario::Market { shock, .. } => self.beta(symbol) * shock,
+            Scenario::Rates { bp, .. } => self.rate_sensitivity(symbol) * bp / 10_000.0,
+        }
+    }
+}
+
+//
+// --------------------
+// Scenario Engine
+// --------------------
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioResult {
+    pub name: String,
+    /// Total P&L across positions (cash is unaffected)
+    pub pnl: f64,
+    /// `pnl` over current equity
+    pub return_pct: f64,
+    pub by_symbol: Vec<(String, f64)>,
+}
+
+// Applies each scenario instantly to the portfolio's current positions
+pub fn run_scenarios(
+    portfolio: &Portfolio,
+    data: &ScenarioData,
+    scenarios: &[Scenario],
+) -> Vec<ScenarioResult> {
+    let equity = portfolio.equity();
+    scenarios
+        .iter()
+        .map(|scenario| {
+            let by_symbol: Vec<(String, f64)> = portfolio
+                .positions()
+                .filter(|(_, p)| p.quantity() != 0.0)
+                .map(|(symbol, p)| {
+                    let pnl = p.market_value() * data.shocked_return(symbol, scenario);
+                    (symbol.clone(), pnl)
+                })
+                .collect();
+            let pnl = by_symbol.iter().map(|(_, v)| v).sum();
+            ScenarioResult {
+                name: scenario.name().to_string(),
+                pnl,
+                return_pct: if equity != 0.0 { pnl / equity } else { 0.0 },
+                by_symbol,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::LotMethod;
+    use crate::test_util::day;
+
+    fn bench(n: u64) -> BTreeMap<NaiveDate, f64> {
+        (0..n)
+            .map(|i| (day(i), 0.01 * ((i * 37 % 11) as f64 / 5.0 - 1.0)))
+            .collect()
+    }
+
+    fn data() -> ScenarioData {
+        let benchmark = bench(60);
+        // twice the benchmark, with full history
+        let levered = benchmark.iter().map(|(d, r)| (*d, 2.0 * r)).collect();
+        // a rate-sensitive fund: loses 7x the yield change
+        let yields: BTreeMap<NaiveDate, f64> = (0..60)
+            .map(|i| (day(i), 0.0001 * ((i * 13 % 7) as f64 - 3.0)))
+            .collect();
+        let bond = yields.iter().map(|(d, dy)| (*d, -7.0 * dy)).collect();
+
+        let mut symbols = HashMap::new();
+        symbols.insert("LEV".to_string(), levered);
+        symbols.insert("BOND".to_string(), bond);
+        // too short to estimate a beta
+        symbols.insert("NEW".to_string(), bench(5));
+
+        ScenarioData {
+            benchmark,
+            symbols,
+            yield_changes: yields,
+            proxy_betas: HashMap::from([("NEW".to_string(), 1.5)]),
+        }
+    }
+
+    fn portfolio() -> Portfolio {
+        let mut p = Portfolio::new(100_000.0, LotMethod::Fifo);
+        for symbol in ["LEV", "BOND", "NEW", "NONE"] {
+            p.apply_fill(symbol, 100.0, 100.0, day(0));
+        }
+        p
+    }
+
+    #[test]
+    fn test_betas_and_proxies() {
+        let data = data();
+        assert!((data.beta("LEV") - 2.0).abs() < 1e-10);
+        assert_eq!(data.beta("NEW"), 1.5);
+        assert_eq!(data.beta("NONE"), 1.0);
+        assert!((data.rate_sensitivity("BOND") + 7.0).abs() < 1e-10);
+        assert_eq!(data.rate_sensitivity("NONE"), 0.0);
+    }
+
+    #[test]
+    fn test_market_and_rate_shocks() {
+        let scenarios = [
+            Scenario::Market {
+                name: "crash".to_string(),
+                shock: -0.2,
+            },
+            Scenario::Rates {
+                name: "+200bp".to_string(),
+                bp: 200.0,
+            },
+        ];
+        let results = run_scenarios(&portfolio(), &data(), &scenarios);
+        let pnl = |r: &ScenarioResult, s: &str| r.by_symbol.iter().find(|(x, _)| x == s).unwrap().1;
+
+        let crash = &results[0];
+        assert!((pnl(crash, "LEV") + 10_000.0 * 0.4).abs() < 1e-6);
+        assert!((pnl(crash, "NEW") + 10_000.0 * 0.3).abs() < 1e-6);
+        assert!((pnl(crash, "NONE") + 10_000.0 * 0.2).abs() < 1e-6);
+        let total: f64 = crash.by_symbol.iter().map(|(_, v)| v).sum();
+        assert!((crash.pnl - total).abs() < 1e-9);
+        assert!((crash.return_pct - crash.pnl / 100_000.0).abs() < 1e-12);
+
+        let rates = &results[1];
+        assert!((pnl(rates, "BOND") + 10_000.0 * 0.14).abs() < 1e-6);
+        assert_eq!(pnl(rates, "NONE"), 0.0);
+    }
+
+    #[test]
+    fn test_historical_replay() {
+        let data = data();
+        let (start, end) = (day(10), day(20));
+        let scenario = Scenario::Historical {
+            name: "window".to_string(),
+            start,
+            end,
+        };
+        let bench_move = ScenarioData::window_return(&data.benchmark, start, end);
+        let lev_move = ScenarioData::window_return(&data.symbols["LEV"], start, end);
+
+        // own history where it covers the window, beta proxy otherwise
+        assert_eq!(data.shocked_return("LEV", &scenario), lev_move);
+        assert!((data.shocked_return("NEW", &scenario) - 1.5 * bench_move).abs() < 1e-12);
+        assert!((data.shocked_return("NONE", &scenario) - bench_move).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_yield_changes_sorts_levels() {
+        let levels = [(day(2), 0.042), (day(0), 0.040), (day(1), 0.041)];
+        let changes = yield_changes(&levels);
+        assert_eq!(changes.len(), 2);
+        assert!((changes[&day(1)] - 0.001).abs() < 1e-12);
+        assert!((changes[&day(2)] - 0.001).abs() < 1e-12);
+    }
+}
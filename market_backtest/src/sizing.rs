@@ -0,0 +1,373 @@
+use crate::indicators::atr;
+use crate::metrics::{self, TRADING_DAYS_PER_YEAR};
+use crate::strategy::{Context, Signal, Strategy};
+use crate::volatility::VolatilityModel;
+use std::collections::VecDeque;
+
+//
+// --------------------
+// Position Sizing
+// --------------------
+// Strategies decide when to be in the market; a sizer decides how many
+// shares. Sizers return whole shares and leave cash limits to the engine.
+pub trait PositionSizer {
+    /// Shares to hold when the strategy enters on the current bar
+    fn quantity(&mut self, ctx: &Context) -> f64;
+
+    /// Called on every bar before the strategy, signal or not
+    fn observe(&mut self, _ctx: &Context) {}
+}
+
+// Any strategy with its entries sized by the sizer: a buy or sell from flat
+// opens a long or short of the sized quantity. Exits, covers and adds to an
+// open position pass through unchanged.
+#[derive(Debug, Clone)]
+pub struct SizedStrategy<S, P> {
+    pub strategy: S,
+    pub sizer: P,
+}
+
+impl<S, P> SizedStrategy<S, P> {
+    pub fn new(strategy: S, sizer: P) -> Self {
+        Self { strategy, sizer }
+    }
+}
+
+impl<S: Strategy, P: PositionSizer> Strategy for SizedStrategy<S, P> {
+    fn on_bar(&mut self, ctx: &Context) -> Signal {
+        self.sizer.observe(ctx);
+        let signal = self.strategy.on_bar(ctx);
+        if ctx.position() != 0.0 || signal == Signal::Hold {
+            return signal;
+        }
+        let qty = self.sizer.quantity(ctx);
+        match signal {
+            _ if qty <= 0.0 => Signal::Hold,
+            Signal::Buy(_) => Signal::Buy(qty),
+            Signal::Sell(_) => Signal::Sell(qty),
+            Signal::Hold => Signal::Hold,
+        }
+    }
+}
+
+// Whole shares worth `value` at the current close
+fn shares(ctx: &Context, value: f64) -> f64 {
+    let price = ctx.current().close;
+    if price > 0.0 && value > 0.0 {
+        (value / price).floor()
+    } else {
+        0.0
+    }
+}
+
+//
+// --------------------
+// Fixed Sizers
+// --------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedQuantity {
+    pub shares: f64,
+}
+
+impl PositionSizer for FixedQuantity {
+    fn quantity(&mut self, _ctx: &Context) -> f64 {
+        self.shares
+    }
+}
+
+// The same cash amount per position, whatever the account is worth
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedNotional {
+    pub notional: f64,
+}
+
+impl PositionSizer for FixedNotional {
+    fn quantity(&mut self, ctx: &Context) -> f64 {
+        shares(ctx, self.notional)
+    }
+}
+
+// A fixed share of current equity, so positions grow and shrink with the
+// account
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedFractional {
+    pub fraction: f64,
+}
+
+impl PositionSizer for FixedFractional {
+    fn quantity(&mut self, ctx: &Context) -> f64 {
+        shares(ctx, self.fraction * ctx.equity())
+    }
+}
+
+//
+// --------------------
+// Kelly
+// --------------------
+// Scales equity by `fraction` of the Kelly fraction of the strategy's own
+// last `lookback` round trips (entry to flat, measured by the change in
+// account value, fees included). Kelly is capped at 1 (no leverage) and
+// floored at 0; until `min_trades` round trips exist, `fallback` of equity
+// is used instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kelly {
+    /// 0.5 is "half Kelly"; full Kelly is very volatile
+    pub fraction: f64,
+    pub lookback: usize,
+    pub min_trades: usize,
+    pub fallback: f64,
+    pnls: VecDeque<f64>,
+    last_position: f64,
+    last_equity: Option<f64>,
+    entry_equity: Option<f64>,
+}
+
+impl Kelly {
+    pub fn new(fraction: f64, lookback: usize) -> Self {
+        Self {
+            fraction,
+            lookback,
+            min_trades: 10,
+            fallback: 0.1,
+            pnls: VecDeque::new(),
+            last_position: 0.0,
+            last_equity: None,
+            entry_equity: None,
+        }
+    }
+
+    /// Share of equity the next position would get
+    pub fn allocation(&self) -> f64 {
+        if self.pnls.len() < self.min_trades.max(1) {
+            return self.fallback;
+        }
+        let pnls: Vec<f64> = self.pnls.iter().copied().collect();
+        let kelly = metrics::kelly_fraction(&pnls).unwrap_or(0.0);
+        self.fraction * kelly.clamp(0.0, 1.0)
+    }
+}
+
+impl PositionSizer for Kelly {
+    fn quantity(&mut self, ctx: &Context) -> f64 {
+        shares(ctx, self.allocation() * ctx.equity())
+    }
+
+    fn observe(&mut self, ctx: &Context) {
+        let position = ctx.position();
+        let equity = ctx.equity();
+        if self.last_position == 0.0 && position != 0.0 {
+            // the fill happened since the last bar, so the trade starts from
+            // the account value before it
+            self.entry_equity = self.last_equity.or(Some(equity));
+        } else if self.last_position != 0.0
+            && position == 0.0
+            && let Some(entry) = self.entry_equity.take()
+        {
+            self.pnls.push_back(equity - entry);
+            if self.pnls.len() > self.lookback.max(1) {
+                self.pnls.pop_front();
+            }
+        }
+        self.last_position = position;
+        self.last_equity = Some(equity);
+    }
+}
+
+//
+// --------------------
+// Volatility Targeting
+// --------------------
+// Holds the amount that would make the position's annualized volatility
+// equal `target`, using the model's next-day forecast, up to
+// `max_leverage` times equity. Buys nothing until the model has enough
+// history to forecast.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolatilityTarget<M> {
+    /// Annualized, e.g. 0.10 for 10%
+    pub target: f64,
+    pub model: M,
+    pub max_leverage: f64,
+}
+
+impl<M: VolatilityModel> PositionSizer for VolatilityTarget<M> {
+    fn quantity(&mut self, ctx: &Context) -> f64 {
+        let Some(daily) = self.model.forecast(ctx.history()).filter(|v| *v > 0.0) else {
+            return 0.0;
+        };
+        let annualized = daily * (TRADING_DAYS_PER_YEAR as f64).sqrt();
+        let weight = (self.target / annualized).min(self.max_leverage);
+        shares(ctx, weight * ctx.equity())
+    }
+}
+
+//
+// --------------------
+// ATR Risk
+// --------------------
+// Risks `risk` of equity per trade on a stop `multiple` ATRs from the entry:
+// shares = risk * equity / (multiple * ATR). Buys nothing until `period + 1`
+// candles exist.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtrRisk {
+    /// e.g. 0.01 to lose 1% of equity if the stop is hit
+    pub risk: f64,
+    pub multiple: f64,
+    pub period: usize,
+}
+
+impl Default for AtrRisk {
+    fn default() -> Self {
+        Self {
+            risk: 0.01,
+            multiple: 2.0,
+            period: 14,
+        }
+    }
+}
+
+impl PositionSizer for AtrRisk {
+    fn quantity(&mut self, ctx: &Context) -> f64 {
+        match atr(ctx.history(), self.period) {
+            Some(range) if range > 0.0 => {
+                (self.risk * ctx.equity() / (self.multiple * range)).floor()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::{self, BacktestConfig, Execution, ShortConfig};
+    use crate::data::Candle;
+    use crate::strategy::BuyAndHold;
+    use crate::test_util::day;
+    use crate::volatility::Historical;
+
+    // Alternates between 100 and 101 with a high-low range of 2
+    fn candles(n: usize) -> Vec<Candle> {
+        (0..n)
+            .map(|i| {
+                let close = 100.0 + (i % 2) as f64;
+                Candle {
+                    date: day(i as u64),
+                    open: close,
+                    high: close + 1.0,
+                    low: close - 1.0,
+                    close,
+                    volume: 1_000.0,
+                }
+            })
+            .collect()
+    }
+
+    fn held<P: PositionSizer>(sizer: P, candles: &[Candle]) -> f64 {
+        let config = BacktestConfig {
+            execution: Execution::SameClose,
+            ..Default::default()
+        };
+        let mut strategy = SizedStrategy::new(BuyAndHold::default(), sizer);
+        backtest::run(candles, &mut strategy, &config)
+            .portfolio
+            .quantity("ASSET")
+    }
+
+    // Shorts on the first bar, covers on the third and goes long on the fourth
+    struct ShortThenLong;
+
+    impl Strategy for ShortThenLong {
+        fn on_bar(&mut self, ctx: &Context) -> Signal {
+            match ctx.index() {
+                0 => Signal::Sell(1.0),
+                2 => Signal::Buy(-ctx.position()),
+                3 => Signal::Buy(1.0),
+                _ => Signal::Hold,
+            }
+        }
+    }
+
+    #[test]
+    fn test_sizes_short_entries_and_passes_covers_through() {
+        let config = BacktestConfig {
+            execution: Execution::SameClose,
+            shorting: Some(ShortConfig::default()),
+            ..Default::default()
+        };
+        let mut strategy = SizedStrategy::new(ShortThenLong, FixedQuantity { shares: 25.0 });
+        let result = backtest::run(&candles(5), &mut strategy, &config);
+
+        // the cover closes the short rather than flipping the account long
+        let fills: Vec<f64> = result.fills.iter().map(|f| f.qty).collect();
+        assert_eq!(fills, vec![-25.0, 25.0, 25.0]);
+        assert_eq!(result.portfolio.quantity("ASSET"), 25.0);
+    }
+
+    #[test]
+    fn test_fixed_sizers() {
+        let data = candles(5);
+        assert_eq!(held(FixedQuantity { shares: 25.0 }, &data), 25.0);
+        assert_eq!(held(FixedNotional { notional: 5_050.0 }, &data), 50.0);
+        // half of the 100,000 starting equity at 100
+        assert_eq!(held(FixedFractional { fraction: 0.5 }, &data), 500.0);
+    }
+
+    #[test]
+    fn test_atr_risk() {
+        // ATR is 2, so a 2-ATR stop is 4 away: 1% of 100,000 / 4
+        let data = candles(20);
+        let mut sizer = AtrRisk::default();
+        let ctx = Context::new(&data, 19, 0.0, 100_000.0);
+        assert_eq!(sizer.quantity(&ctx), 250.0);
+        let early = Context::new(&data, 5, 0.0, 100_000.0);
+        assert_eq!(sizer.quantity(&early), 0.0);
+    }
+
+    #[test]
+    fn test_volatility_target() {
+        let data = candles(40);
+        let mut sizer = VolatilityTarget {
+            target: 0.10,
+            model: Historical { window: 20 },
+            max_leverage: 1.0,
+        };
+        let ctx = Context::new(&data, 39, 0.0, 100_000.0);
+        let returns =
+            metrics::log_returns(&data[19..].iter().map(|c| c.close).collect::<Vec<f64>>());
+        let (_, daily) = metrics::calc_stats(&returns).unwrap();
+        let weight = 0.10 / (daily * 252f64.sqrt());
+        assert!(weight < 1.0);
+        assert_eq!(sizer.quantity(&ctx), (weight * 100_000.0 / 101.0).floor());
+
+        // a volatility far below target is capped at max leverage
+        sizer.target = 10.0;
+        assert_eq!(sizer.quantity(&ctx), (100_000.0f64 / 101.0).floor());
+        let early = Context::new(&data, 10, 0.0, 100_000.0);
+        assert_eq!(sizer.quantity(&early), 0.0);
+    }
+
+    #[test]
+    fn test_kelly_learns_from_round_trips() {
+        let data = candles(40);
+        let mut sizer = Kelly::new(0.5, 20);
+        sizer.min_trades = 5;
+        let mut cash = 100_000.0;
+
+        // three winners of 200 and two losers of 100, each flat, in, flat
+        for (i, pnl) in [200.0, -100.0, 200.0, -100.0, 200.0].iter().enumerate() {
+            assert_eq!(sizer.allocation(), 0.1);
+            let bar = 3 * i;
+            sizer.observe(&Context::new(&data, bar, 0.0, cash));
+            let entry_cash = cash - 100.0 * data[bar + 1].close;
+            sizer.observe(&Context::new(&data, bar + 1, 100.0, entry_cash));
+            cash += pnl;
+            sizer.observe(&Context::new(&data, bar + 2, 0.0, cash));
+        }
+        let bar = 15;
+        // Kelly = 0.6 - 0.4 / 2 = 0.4, halved
+        assert!((sizer.allocation() - 0.2).abs() < 1e-12);
+        let ctx = Context::new(&data, bar, 0.0, cash);
+        let expected = (0.2 * cash / data[bar].close).floor();
+        assert_eq!(sizer.quantity(&ctx), expected);
+    }
+}
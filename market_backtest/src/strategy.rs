@@ -0,0 +1,400 @@
+use crate::backtest::Portfolio;
+use crate::covariance::{self, Universe};
+use crate::data::Candle;
+use crate::indicators::sma;
+use crate::metrics;
+use crate::optimize::{self, Constraints, Objective};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+//
+// --------------------
+// Strategy Context
+// --------------------
+// What a strategy sees on bar `t`: the candles up to and including `t`, plus
+// its own account state. There is no way to reach a candle after `t` from here.
+pub struct Context<'a> {
+    history: &'a [Candle],
+    position: f64,
+    cash: f64,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(candles: &'a [Candle], bar: usize, position: f64, cash: f64) -> Self {
+        Self {
+            history: &candles[..=bar],
+            position,
+            cash,
+        }
+    }
+
+    /// Candles from the start of the data up to and including the current bar
+    pub fn history(&self) -> &'a [Candle] {
+        self.history
+    }
+
+    /// The bar the strategy is deciding on (its close is already known)
+    pub fn current(&self) -> &'a Candle {
+        &self.history[self.history.len() - 1]
+    }
+
+    /// Index of the current bar in the full data set
+    pub fn index(&self) -> usize {
+        self.history.len() - 1
+    }
+
+    /// Shares currently held
+    pub fn position(&self) -> f64 {
+        self.position
+    }
+
+    /// Cash currently available
+    pub fn cash(&self) -> f64 {
+        self.cash
+    }
+
+    /// Cash plus the position marked at the current close
+    pub fn equity(&self) -> f64 {
+        self.cash + self.position * self.current().close
+    }
+}
+
+//
+// --------------------
+// Signals & Strategy Trait
+// --------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Signal {
+    Hold,
+    Buy(f64),
+    Sell(f64),
+}
+
+pub trait Strategy {
+    /// Called once per bar after its close; the returned signal is executed
+    /// according to the engine's `Execution` setting.
+    fn on_bar(&mut self, ctx: &Context) -> Signal;
+}
+
+//
+// --------------------
+// Buy & Hold
+// --------------------
+// Spends all available cash on the first bar and never trades again
+#[derive(Debug, Default)]
+pub struct BuyAndHold {
+    invested: bool,
+}
+
+impl Strategy for BuyAndHold {
+    fn on_bar(&mut self, ctx: &Context) -> Signal {
+        if self.invested {
+            return Signal::Hold;
+        }
+        self.invested = true;
+        Signal::Buy((ctx.cash() / ctx.current().close).floor())
+    }
+}
+
+//
+// --------------------
+// Moving Average Crossover
+// --------------------
+// Fully invested while the fast SMA of closes is above the slow one, flat
+// otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SmaCrossover {
+    pub fast: usize,
+    pub slow: usize,
+}
+
+impl Strategy for SmaCrossover {
+    fn on_bar(&mut self, ctx: &Context) -> Signal {
+        let closes: Vec<f64> = ctx.history().iter().map(|c| c.close).collect();
+        let (Some(fast), Some(slow)) = (sma(&closes, self.fast), sma(&closes, self.slow)) else {
+            return Signal::Hold;
+        };
+
+        if fast > slow && ctx.position() == 0.0 {
+            Signal::Buy((ctx.cash() / ctx.current().close).floor())
+        } else if fast < slow && ctx.position() > 0.0 {
+            Signal::Sell(ctx.position())
+        } else {
+            Signal::Hold
+        }
+    }
+}
+
+//
+// --------------------
+// Portfolio Strategies
+// --------------------
+// Multi-asset counterpart of `Context` for `backtest::run_portfolio`: every
+// symbol's candles up to the current date, plus the whole account
+pub struct PortfolioContext<'a> {
+    date: NaiveDate,
+    histories: BTreeMap<&'a str, &'a [Candle]>,
+    portfolio: &'a Portfolio,
+}
+
+impl<'a> PortfolioContext<'a> {
+    pub(crate) fn new(
+        date: NaiveDate,
+        histories: BTreeMap<&'a str, &'a [Candle]>,
+        portfolio: &'a Portfolio,
+    ) -> Self {
+        Self {
+            date,
+            histories,
+            portfolio,
+        }
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.histories.keys().copied()
+    }
+
+    /// The symbol's candles up to and including the current date
+    pub fn history(&self, symbol: &str) -> Option<&'a [Candle]> {
+        self.histories.get(symbol).copied()
+    }
+
+    /// Current close
+    pub fn price(&self, symbol: &str) -> Option<f64> {
+        self.history(symbol)?.last().map(|c| c.close)
+    }
+
+    pub fn quantity(&self, symbol: &str) -> f64 {
+        self.portfolio.quantity(symbol)
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.portfolio.cash
+    }
+
+    /// Cash plus every position marked at the current close
+    pub fn equity(&self) -> f64 {
+        self.portfolio.cash
+            + self
+                .portfolio
+                .positions()
+                .map(|(symbol, p)| p.quantity() * self.price(symbol).unwrap_or(p.last_price))
+                .sum::<f64>()
+    }
+
+    /// Share of equity held in `symbol` at the current close
+    pub fn weight(&self, symbol: &str) -> f64 {
+        let equity = self.equity();
+        match self.price(symbol) {
+            Some(price) if equity != 0.0 => self.quantity(symbol) * price / equity,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub symbol: String,
+    /// Shares to trade; positive buys, negative sells
+    pub qty: f64,
+    /// Trading unit; a buy the cash can't cover is cut to whole lots
+    pub lot: f64,
+}
+
+pub trait PortfolioStrategy {
+    /// Called once per date after the closes of every symbol; the orders are
+    /// executed according to the engine's `Execution` setting.
+    fn on_bar(&mut self, ctx: &PortfolioContext) -> Vec<Order>;
+}
+
+//
+// --------------------
+// Rebalancing
+// --------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    Daily,
+    /// First bar of each ISO week
+    Weekly,
+    /// First bar of each calendar month
+    Monthly,
+    /// First bar of each calendar quarter
+    Quarterly,
+    /// Whenever any weight is further than this from its target
+    /// (0.05 = 5 percentage points)
+    Drift(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Targets {
+    /// Static weights, e.g. 60/40; symbols held but not listed are sold
+    Fixed(Vec<(String, f64)>),
+    /// Re-estimated on every rebalance from the last `lookback` daily
+    /// returns of every symbol (Ledoit–Wolf covariance, mean returns)
+    Optimized {
+        objective: Objective,
+        constraints: Constraints,
+        lookback: usize,
+    },
+}
+
+// Holds target weights across the universe and trades back to them when the
+// schedule says so. Only the difference from the current holdings is
+// traded, in whole lots rounded toward zero, sells first so they fund the
+// buys. A rebalance that comes due before the targets can be computed (e.g.
+// before the optimizer's lookback is met) waits for the first bar they can.
+#[derive(Debug, Clone)]
+pub struct Rebalance {
+    pub targets: Targets,
+    pub schedule: Schedule,
+    /// Shares per tradable lot by symbol; 1 when missing
+    pub lot_sizes: HashMap<String, f64>,
+    /// Orders worth less than this are skipped
+    pub min_trade: f64,
+    last_date: Option<NaiveDate>,
+    /// A scheduled rebalance has not been carried out yet
+    pending: bool,
+    /// Targets set at the last rebalance, which drift is measured against
+    weights: Option<Vec<(String, f64)>>,
+}
+
+impl Rebalance {
+    pub fn new(targets: Targets, schedule: Schedule) -> Self {
+        Self {
+            targets,
+            schedule,
+            lot_sizes: HashMap::new(),
+            min_trade: 0.0,
+            last_date: None,
+            pending: false,
+            weights: None,
+        }
+    }
+
+    fn lot(&self, symbol: &str) -> f64 {
+        self.lot_sizes.get(symbol).copied().unwrap_or(1.0)
+    }
+
+    fn is_scheduled(&self, previous: Option<NaiveDate>, date: NaiveDate) -> bool {
+        let Some(previous) = previous else {
+            return true;
+        };
+        match self.schedule {
+            Schedule::Daily => true,
+            Schedule::Weekly => previous.iso_week() != date.iso_week(),
+            Schedule::Monthly => (previous.year(), previous.month()) != (date.year(), date.month()),
+            Schedule::Quarterly => {
+                (previous.year(), previous.month0() / 3) != (date.year(), date.month0() / 3)
+            }
+            Schedule::Drift(_) => false,
+        }
+    }
+
+    /// Target weight per symbol on the current bar, if it can be computed
+    pub fn target_weights(&self, ctx: &PortfolioContext) -> Option<Vec<(String, f64)>> {
+        match &self.targets {
+            Targets::Fixed(weights) => Some(weights.clone()),
+            Targets::Optimized {
+                objective,
+                constraints,
+                lookback,
+            } => {
+                let series: Vec<(&str, BTreeMap<NaiveDate, f64>)> = ctx
+                    .symbols()
+                    .map(|symbol| {
+                        let history = ctx.history(symbol).unwrap_or_default();
+                        let recent = &history[history.len().saturating_sub(lookback + 1)..];
+                        let closes: Vec<f64> = recent.iter().map(|c| c.close).collect();
+                        let dates: Vec<NaiveDate> = recent.iter().skip(1).map(|c| c.date).collect();
+                        let returns = metrics::simple_returns(&closes);
+                        (symbol, dates.into_iter().zip(returns).collect())
+                    })
+                    .collect();
+                let refs: Vec<(&str, &BTreeMap<NaiveDate, f64>)> =
+                    series.iter().map(|(s, m)| (*s, m)).collect();
+                let universe = Universe::align(&refs);
+                if universe.observations() < *lookback {
+                    return None;
+                }
+                let cov = covariance::ledoit_wolf(&universe)?.covariance;
+                let allocation =
+                    optimize::optimize(*objective, &cov, &universe.mean_returns(), constraints)?;
+                Some(allocation.iter().map(|(s, w)| (s.to_string(), w)).collect())
+            }
+        }
+    }
+
+    /// Orders that bring the holdings to `targets` at the current closes
+    pub fn orders(&self, ctx: &PortfolioContext, targets: &[(String, f64)]) -> Vec<Order> {
+        let equity = ctx.equity();
+        let held = ctx.symbols().filter(|s| ctx.quantity(s) != 0.0);
+        let symbols: BTreeSet<&str> = targets
+            .iter()
+            .map(|(s, _)| s.as_str())
+            .chain(held)
+            .collect();
+
+        let mut orders: Vec<Order> = symbols
+            .into_iter()
+            .filter_map(|symbol| {
+                let price = ctx.price(symbol).filter(|p| *p > 0.0)?;
+                let lot = self.lot(symbol);
+                let diff = target_weight(targets, symbol) * equity / price - ctx.quantity(symbol);
+                let qty = (diff / lot).trunc() * lot;
+                (qty != 0.0 && (qty * price).abs() >= self.min_trade).then(|| Order {
+                    symbol: symbol.to_string(),
+                    qty,
+                    lot,
+                })
+            })
+            .collect();
+        orders.sort_by(|a, b| a.qty.total_cmp(&b.qty));
+        orders
+    }
+}
+
+impl PortfolioStrategy for Rebalance {
+    fn on_bar(&mut self, ctx: &PortfolioContext) -> Vec<Order> {
+        let previous = self.last_date.replace(ctx.date());
+        if self.is_scheduled(previous, ctx.date()) {
+            self.pending = true;
+        }
+        if let Schedule::Drift(band) = self.schedule
+            && let Some(weights) = &self.weights
+            && drifted(ctx, weights, band)
+        {
+            self.pending = true;
+        }
+        if !self.pending {
+            return Vec::new();
+        }
+
+        let Some(targets) = self.target_weights(ctx) else {
+            return Vec::new();
+        };
+        self.pending = false;
+        let orders = self.orders(ctx, &targets);
+        self.weights = Some(targets);
+        orders
+    }
+}
+
+fn target_weight(targets: &[(String, f64)], symbol: &str) -> f64 {
+    targets
+        .iter()
+        .find(|(s, _)| s == symbol)
+        .map_or(0.0, |(_, w)| *w)
+}
+
+// True before the first trade, or once any weight is outside the band
+fn drifted(ctx: &PortfolioContext, targets: &[(String, f64)], band: f64) -> bool {
+    let invested = ctx.symbols().any(|s| ctx.quantity(s) != 0.0);
+    !invested
+        || ctx
+            .symbols()
+            .any(|symbol| (ctx.weight(symbol) - target_weight(targets, symbol)).abs() > band)
+}
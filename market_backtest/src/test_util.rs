@@ -0,0 +1,11 @@
+use chrono::NaiveDate;
+
+// Date fixtures shared by the unit tests: consecutive calendar days from
+// 2025-01-01
+pub fn day(i: u64) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 1, 1).unwrap() + chrono::Days::new(i)
+}
+
+pub fn dates(n: usize) -> Vec<NaiveDate> {
+    (0..n as u64).map(day).collect()
+}
@@ -0,0 +1,525 @@
+use crate::data::Candle;
+use crate::metrics;
+
+//
+// --------------------
+// Volatility Models
+// --------------------
+// Everything here is daily volatility (standard deviation of daily log
+// returns); multiply by sqrt(TRADING_DAYS_PER_YEAR) to annualize.
+pub trait VolatilityModel {
+    /// Forecast of the next bar's volatility from the candles seen so far
+    fn forecast(&self, history: &[Candle]) -> Option<f64>;
+}
+
+fn close_returns(history: &[Candle]) -> Vec<f64> {
+    let closes: Vec<f64> = history.iter().map(|c| c.close).collect();
+    metrics::log_returns(&closes)
+}
+
+//
+// --------------------
+// Historical
+// --------------------
+// Sample standard deviation of the last `window` close-to-close returns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Historical {
+    pub window: usize,
+}
+
+impl VolatilityModel for Historical {
+    fn forecast(&self, history: &[Candle]) -> Option<f64> {
+        let returns = close_returns(history);
+        if returns.len() < self.window {
+            return None;
+        }
+        let (_, std_dev) = metrics::calc_stats(&returns[returns.len() - self.window..])?;
+        Some(std_dev)
+    }
+}
+
+//
+// --------------------
+// EWMA (RiskMetrics)
+// --------------------
+// sigma²_t = lambda * sigma²_{t-1} + (1 - lambda) * r²_{t-1}, seeded with the
+// mean squared return of the first `warmup` returns. RiskMetrics uses
+// lambda = 0.94 for daily data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ewma {
+    pub lambda: f64,
+    /// Returns used only to seed the recursion
+    pub warmup: usize,
+}
+
+impl Default for Ewma {
+    fn default() -> Self {
+        Self {
+            lambda: 0.94,
+            warmup: 20,
+        }
+    }
+}
+
+impl Ewma {
+    /// Variance for every return after the warm-up (using only earlier
+    /// returns) followed by the one-step-ahead forecast, so
+    /// `returns.len() - warmup + 1` values. Empty without `warmup` returns.
+    pub fn variances(&self, returns: &[f64]) -> Vec<f64> {
+        let warmup = self.warmup.max(1);
+        if returns.len() < warmup {
+            return Vec::new();
+        }
+        let (seed, rest) = returns.split_at(warmup);
+        let mut var = seed.iter().map(|r| r * r).sum::<f64>() / warmup as f64;
+        let mut out = Vec::with_capacity(rest.len() + 1);
+        out.push(var);
+        for r in rest {
+            var = self.lambda * var + (1.0 - self.lambda) * r * r;
+            out.push(var);
+        }
+        out
+    }
+}
+
+impl VolatilityModel for Ewma {
+    fn forecast(&self, history: &[Candle]) -> Option<f64> {
+        self.variances(&close_returns(history))
+            .last()
+            .map(|v| v.sqrt())
+    }
+}
+
+//
+// --------------------
+// GARCH(1,1)
+// --------------------
+// sigma²_t = omega + alpha * e²_{t-1} + beta * sigma²_{t-1} on demeaned
+// returns e. Fitted by Gaussian maximum likelihood with variance targeting
+// (omega pinned so the long-run variance equals the sample variance).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Garch {
+    pub omega: f64,
+    pub alpha: f64,
+    pub beta: f64,
+    /// Mean removed from returns before fitting
+    pub mean: f64,
+}
+
+impl Garch {
+    /// Needs enough data for the likelihood to be informative (100+ returns)
+    pub fn fit(returns: &[f64]) -> Option<Garch> {
+        if returns.len() < 100 {
+            return None;
+        }
+        let (mean, std_dev) = metrics::calc_stats(returns)?;
+        let sample_var = std_dev * std_dev;
+        if sample_var == 0.0 {
+            return None;
+        }
+        let demeaned: Vec<f64> = returns.iter().map(|r| r - mean).collect();
+
+        let model = |p: &[f64]| Garch {
+            omega: sample_var * (1.0 - p[0] - p[1]),
+            alpha: p[0],
+            beta: p[1],
+            mean,
+        };
+        let objective = |p: &[f64]| {
+            if p[0] <= 0.0 || p[1] < 0.0 || p[0] + p[1] >= 0.9999 {
+                return f64::INFINITY;
+            }
+            -model(p).log_likelihood_demeaned(&demeaned, sample_var)
+        };
+
+        let best = nelder_mead(objective, &[0.05, 0.90], 0.05, 500);
+        objective(&best).is_finite().then(|| model(&best))
+    }
+
+    /// alpha + beta; shocks decay at this rate per day
+    pub fn persistence(&self) -> f64 {
+        self.alpha + self.beta
+    }
+
+    pub fn long_run_variance(&self) -> f64 {
+        self.omega / (1.0 - self.persistence())
+    }
+
+    /// Conditional variance for every return followed by the one-step-ahead
+    /// forecast, so `returns.len() + 1` values
+    pub fn variances(&self, returns: &[f64]) -> Vec<f64> {
+        let mut var = self.long_run_variance();
+        let mut out = Vec::with_capacity(returns.len() + 1);
+        out.push(var);
+        for r in returns {
+            let e = r - self.mean;
+            var = self.omega + self.alpha * e * e + self.beta * var;
+            out.push(var);
+        }
+        out
+    }
+
+    /// Variance expected `horizon` days after the end of `returns`
+    /// (horizon 1 is the next day)
+    pub fn forecast_variance(&self, returns: &[f64], horizon: usize) -> Option<f64> {
+        let next = *self.variances(returns).last()?;
+        let long_run = self.long_run_variance();
+        let steps = horizon.saturating_sub(1) as i32;
+        Some(long_run + self.persistence().powi(steps) * (next - long_run))
+    }
+
+    pub fn log_likelihood(&self, returns: &[f64]) -> f64 {
+        let demeaned: Vec<f64> = returns.iter().map(|r| r - self.mean).collect();
+        self.log_likelihood_demeaned(&demeaned, self.long_run_variance())
+    }
+
+    fn log_likelihood_demeaned(&self, demeaned: &[f64], start_var: f64) -> f64 {
+        let mut var = start_var;
+        let mut ll = 0.0;
+        for e in demeaned {
+            if var <= 0.0 {
+                return f64::NEG_INFINITY;
+            }
+            ll -= 0.5 * ((2.0 * std::f64::consts::PI).ln() + var.ln() + e * e / var);
+            var = self.omega + self.alpha * e * e + self.beta * var;
+        }
+        ll
+    }
+}
+
+// Refits on the full history every call; the slowest model here
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GarchForecast;
+
+impl VolatilityModel for GarchForecast {
+    fn forecast(&self, history: &[Candle]) -> Option<f64> {
+        let returns = close_returns(history);
+        let model = Garch::fit(&returns)?;
+        model.forecast_variance(&returns, 1).map(f64::sqrt)
+    }
+}
+
+// Downhill simplex minimization from `start`, initial step `step` per axis
+fn nelder_mead<F>(f: F, start: &[f64], step: f64, max_iter: usize) -> Vec<f64>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    let n = start.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = (0..=n)
+        .map(|i| {
+            let mut p = start.to_vec();
+            if i > 0 {
+                p[i - 1] += step;
+            }
+            let v = f(&p);
+            (p, v)
+        })
+        .collect();
+
+    let blend = |a: &[f64], b: &[f64], t: f64| -> Vec<f64> {
+        a.iter().zip(b).map(|(x, y)| x + t * (y - x)).collect()
+    };
+
+    for _ in 0..max_iter {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        if (simplex[n].1 - simplex[0].1).abs() < 1e-10 {
+            break;
+        }
+        let centroid: Vec<f64> = (0..n)
+            .map(|j| simplex[..n].iter().map(|(p, _)| p[j]).sum::<f64>() / n as f64)
+            .collect();
+        let worst = simplex[n].0.clone();
+
+        let reflected = blend(&centroid, &worst, -1.0);
+        let fr = f(&reflected);
+        if fr < simplex[0].1 {
+            let expanded = blend(&centroid, &worst, -2.0);
+            let fe = f(&expanded);
+            simplex[n] = if fe < fr {
+                (expanded, fe)
+            } else {
+                (reflected, fr)
+            };
+        } else if fr < simplex[n - 1].1 {
+            simplex[n] = (reflected, fr);
+        } else {
+            let contracted = blend(&centroid, &worst, 0.5);
+            let fc = f(&contracted);
+            if fc < simplex[n].1 {
+                simplex[n] = (contracted, fc);
+            } else {
+                // shrink toward the best point
+                let best = simplex[0].0.clone();
+                for vertex in simplex.iter_mut().skip(1) {
+                    vertex.0 = blend(&best, &vertex.0, 0.5);
+                    vertex.1 = f(&vertex.0);
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex.swap_remove(0).0
+}
+
+//
+// --------------------
+// Range-based Estimators
+// --------------------
+// Use each bar's open, high, low and close, so they need far fewer bars than
+// close-to-close estimates for the same precision.
+fn ln_ratio(a: f64, b: f64) -> f64 {
+    (a / b).ln()
+}
+
+// High-low range only; assumes no drift and no overnight gaps
+pub fn parkinson(candles: &[Candle]) -> Option<f64> {
+    if candles.is_empty() {
+        return None;
+    }
+    let sum: f64 = candles
+        .iter()
+        .map(|c| ln_ratio(c.high, c.low).powi(2))
+        .sum();
+    Some((sum / (4.0 * 2f64.ln() * candles.len() as f64)).sqrt())
+}
+
+// Adds the open-to-close move to Parkinson's range; assumes no drift
+pub fn garman_klass(candles: &[Candle]) -> Option<f64> {
+    if candles.is_empty() {
+        return None;
+    }
+    let sum: f64 = candles
+        .iter()
+        .map(|c| {
+            0.5 * ln_ratio(c.high, c.low).powi(2)
+                - (2.0 * 2f64.ln() - 1.0) * ln_ratio(c.close, c.open).powi(2)
+        })
+        .sum();
+    Some((sum / candles.len() as f64).max(0.0).sqrt())
+}
+
+// Combines overnight, open-to-close and Rogers–Satchell variances; handles
+// both drift and opening gaps. Needs 3+ candles (the first only supplies a
+// previous close).
+pub fn yang_zhang(candles: &[Candle]) -> Option<f64> {
+    if candles.len() < 3 {
+        return None;
+    }
+    let bars = &candles[1..];
+    let overnight: Vec<f64> = candles
+        .windows(2)
+        .map(|w| ln_ratio(w[1].open, w[0].close))
+        .collect();
+    let open_close: Vec<f64> = bars.iter().map(|c| ln_ratio(c.close, c.open)).collect();
+    let rogers_satchell = bars
+        .iter()
+        .map(|c| {
+            ln_ratio(c.high, c.close) * ln_ratio(c.high, c.open)
+                + ln_ratio(c.low, c.close) * ln_ratio(c.low, c.open)
+        })
+        .sum::<f64>()
+        / bars.len() as f64;
+
+    let n = bars.len() as f64;
+    let k = 0.34 / (1.34 + (n + 1.0) / (n - 1.0));
+    let (_, overnight_sd) = metrics::calc_stats(&overnight)?;
+    let (_, open_close_sd) = metrics::calc_stats(&open_close)?;
+    let var = overnight_sd.powi(2) + k * open_close_sd.powi(2) + (1.0 - k) * rogers_satchell;
+    Some(var.max(0.0).sqrt())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeEstimator {
+    Parkinson,
+    GarmanKlass,
+    YangZhang,
+}
+
+// A range estimator over the last `window` candles, used as the forecast
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RangeVolatility {
+    pub estimator: RangeEstimator,
+    pub window: usize,
+}
+
+impl VolatilityModel for RangeVolatility {
+    fn forecast(&self, history: &[Candle]) -> Option<f64> {
+        if history.len() < self.window {
+            return None;
+        }
+        let recent = &history[history.len() - self.window..];
+        match self.estimator {
+            RangeEstimator::Parkinson => parkinson(recent),
+            RangeEstimator::GarmanKlass => garman_klass(recent),
+            RangeEstimator::YangZhang => yang_zhang(recent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+    use rand_distr::{Distribution, Normal};
+
+    fn candle(open: f64, high: f64, low: f64, close: f64) -> Candle {
+        Candle {
+            date: NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume: 100.0,
+        }
+    }
+
+    // GARCH(1,1) path with omega 1e-6, alpha 0.1, beta 0.85
+    fn garch_returns(n: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let z = Normal::new(0.0, 1.0).unwrap();
+        let (omega, alpha, beta) = (1e-6, 0.1, 0.85);
+        let mut var: f64 = omega / (1.0 - alpha - beta);
+        (0..n)
+            .map(|_| {
+                let r = var.sqrt() * z.sample(&mut rng);
+                var = omega + alpha * r * r + beta * var;
+                r
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ewma_recursion() {
+        let ewma = Ewma {
+            lambda: 0.9,
+            warmup: 2,
+        };
+        let vars = ewma.variances(&[0.01, 0.03, 0.02, 0.05]);
+        assert_eq!(vars.len(), 3);
+        // seeded from the first two returns only
+        assert!((vars[0] - 5e-4).abs() < 1e-15);
+        assert!((vars[1] - (0.9 * 5e-4 + 0.1 * 4e-4)).abs() < 1e-15);
+        assert!((vars[2] - (0.9 * vars[1] + 0.1 * 25e-4)).abs() < 1e-15);
+        // later returns never change earlier variances
+        assert_eq!(ewma.variances(&[0.01, 0.03, 0.02, 0.5])[..2], vars[..2]);
+        assert!(ewma.variances(&[0.01]).is_empty());
+    }
+
+    #[test]
+    fn test_garch_fit_recovers_parameters() {
+        let returns = garch_returns(5000);
+        let model = Garch::fit(&returns).unwrap();
+        assert!((model.alpha - 0.1).abs() < 0.05, "alpha {}", model.alpha);
+        assert!((model.beta - 0.85).abs() < 0.07, "beta {}", model.beta);
+        assert!(model.persistence() < 1.0);
+
+        // fitted beats a constant-variance model
+        let flat = Garch {
+            omega: model.long_run_variance(),
+            alpha: 0.0,
+            beta: 0.0,
+            mean: model.mean,
+        };
+        assert!(model.log_likelihood(&returns) > flat.log_likelihood(&returns));
+        assert!(Garch::fit(&returns[..50]).is_none());
+    }
+
+    #[test]
+    fn test_garch_forecast_reverts_to_long_run() {
+        let model = Garch {
+            omega: 1e-6,
+            alpha: 0.1,
+            beta: 0.85,
+            mean: 0.0,
+        };
+        // after a large shock the forecast starts high and decays
+        let returns = [0.0, 0.0, 0.08];
+        let one = model.forecast_variance(&returns, 1).unwrap();
+        let ten = model.forecast_variance(&returns, 10).unwrap();
+        let far = model.forecast_variance(&returns, 1000).unwrap();
+        assert!(one > ten && ten > far);
+        assert!((far - model.long_run_variance()).abs() < 1e-12);
+        assert_eq!(one, *model.variances(&returns).last().unwrap());
+    }
+
+    #[test]
+    fn test_range_estimators() {
+        // no range, no move
+        let flat: Vec<Candle> = (0..5).map(|_| candle(10.0, 10.0, 10.0, 10.0)).collect();
+        assert_eq!(parkinson(&flat), Some(0.0));
+        assert_eq!(garman_klass(&flat), Some(0.0));
+        assert_eq!(yang_zhang(&flat), Some(0.0));
+
+        let bars: Vec<Candle> = (0..4).map(|_| candle(100.0, 102.0, 99.0, 101.0)).collect();
+        let hl = (102.0f64 / 99.0).ln();
+        let co = (101.0f64 / 100.0).ln();
+        let expected_p = (hl * hl / (4.0 * 2f64.ln())).sqrt();
+        assert!((parkinson(&bars).unwrap() - expected_p).abs() < 1e-12);
+        let expected_gk = (0.5 * hl * hl - (2.0 * 2f64.ln() - 1.0) * co * co).sqrt();
+        assert!((garman_klass(&bars).unwrap() - expected_gk).abs() < 1e-12);
+
+        assert!(parkinson(&[]).is_none());
+        assert!(yang_zhang(&bars[..2]).is_none());
+    }
+
+    #[test]
+    fn test_range_estimators_track_true_volatility() {
+        // intraday random walks with 1% daily volatility
+        let mut rng = StdRng::seed_from_u64(11);
+        let step = Normal::new(0.0, 0.01 / 50f64.sqrt()).unwrap();
+        let mut price = 100.0f64;
+        let candles: Vec<Candle> = (0..500)
+            .map(|_| {
+                let open = price;
+                let (mut high, mut low) = (open, open);
+                for _ in 0..50 {
+                    price *= step.sample(&mut rng).exp();
+                    high = high.max(price);
+                    low = low.min(price);
+                }
+                candle(open, high, low, price)
+            })
+            .collect();
+
+        for estimate in [
+            parkinson(&candles),
+            garman_klass(&candles),
+            yang_zhang(&candles),
+        ] {
+            let vol = estimate.unwrap();
+            // discrete sampling understates the true range a little
+            assert!((0.008..0.0115).contains(&vol), "vol {}", vol);
+        }
+    }
+
+    #[test]
+    fn test_models_forecast_from_history() {
+        let candles: Vec<Candle> = garch_returns(300)
+            .iter()
+            .scan(100.0, |p, r| {
+                *p *= f64::exp(*r);
+                Some(candle(*p, *p * 1.01, *p * 0.99, *p))
+            })
+            .collect();
+
+        let models: [(&str, &dyn VolatilityModel); 4] = [
+            ("historical", &Historical { window: 20 }),
+            ("ewma", &Ewma::default()),
+            ("garch", &GarchForecast),
+            (
+                "range",
+                &RangeVolatility {
+                    estimator: RangeEstimator::YangZhang,
+                    window: 20,
+                },
+            ),
+        ];
+        for (name, model) in models {
+            let vol = model.forecast(&candles).unwrap();
+            assert!(vol > 0.0 && vol < 0.1, "{} {}", name, vol);
+        }
+        assert!(Historical { window: 20 }.forecast(&candles[..10]).is_none());
+    }
+}